@@ -54,7 +54,7 @@ impl Hooks for App {
 
         if ctx.environment != Environment::Test {
             initializers.push(Box::new(
-                loco_extras::initializers::prometheus::AxumPrometheusInitializer,
+                loco_extras::initializers::prometheus::AxumPrometheusInitializer::default(),
             ));
         }
 
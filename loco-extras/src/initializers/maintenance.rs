@@ -0,0 +1,173 @@
+//! [Initializer] to put the application in maintenance mode at runtime.
+//!
+//! While maintenance mode is enabled, every route responds with
+//! `503 Service Unavailable`, except the health routes (`/_health`, `/_ping`
+//! and `/_readiness` by default), so orchestration keeps working.
+//!
+//! Maintenance mode is toggled with a [`MaintenanceMode`] handle, available
+//! from [`MaintenanceInitializer::mode`] and as a request extension, so an
+//! admin endpoint can flip it:
+//!
+//! ```rust,ignore
+//! async fn toggle(Extension(mode): Extension<MaintenanceMode>) -> Result<Response> {
+//!     mode.enable();
+//!     format::empty()
+//! }
+//! ```
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    Extension, Router,
+};
+use loco_rs::{controller::ErrorDetail, prelude::*};
+
+/// A shared maintenance mode flag.
+#[derive(Clone, Default)]
+pub struct MaintenanceMode(Arc<AtomicBool>);
+
+impl MaintenanceMode {
+    /// Start responding with `503 Service Unavailable`.
+    pub fn enable(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Resume serving requests.
+    pub fn disable(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct MaintenanceInitializer {
+    mode: MaintenanceMode,
+    bypass: Vec<String>,
+}
+
+impl Default for MaintenanceInitializer {
+    fn default() -> Self {
+        Self {
+            mode: MaintenanceMode::default(),
+            bypass: vec![
+                "/_health".to_string(),
+                "/_ping".to_string(),
+                "/_readiness".to_string(),
+            ],
+        }
+    }
+}
+
+impl MaintenanceInitializer {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given maintenance mode flag, e.g. to keep a handle to it.
+    #[must_use]
+    pub fn with_mode(mut self, mode: MaintenanceMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Add a path that keeps being served during maintenance.
+    #[must_use]
+    pub fn bypass(mut self, path: &str) -> Self {
+        self.bypass.push(path.to_string());
+        self
+    }
+
+    /// Get a handle to the maintenance mode flag.
+    #[must_use]
+    pub fn mode(&self) -> MaintenanceMode {
+        self.mode.clone()
+    }
+}
+
+#[derive(Clone)]
+struct MaintenanceState {
+    mode: MaintenanceMode,
+    bypass: Arc<Vec<String>>,
+}
+
+async fn maintenance(
+    State(state): State<MaintenanceState>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if state.mode.is_enabled() && !state.bypass.iter().any(|p| p == request.uri().path()) {
+        return format::render()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .json(ErrorDetail::new(
+                "maintenance",
+                "The service is under maintenance, please try again later",
+            ));
+    }
+    Ok(next.run(request).await)
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone> Initializer<T> for MaintenanceInitializer {
+    fn name(&self) -> String {
+        "maintenance".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, _ctx: &AppContext<T>) -> Result<Router> {
+        let state = MaintenanceState {
+            mode: self.mode.clone(),
+            bypass: Arc::new(self.bypass.clone()),
+        };
+        Ok(router
+            .layer(middleware::from_fn_with_state(state, maintenance))
+            .layer(Extension(self.mode.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, routing::get};
+    use loco_rs::tests_cfg;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn status(router: &Router, path: &str) -> StatusCode {
+        router
+            .clone()
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn can_toggle_maintenance_mode() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let initializer = MaintenanceInitializer::new();
+        let mode = initializer.mode();
+        let router = Router::new()
+            .route("/", get(|| async { "home" }))
+            .route("/_health", get(|| async { "ok" }));
+        let router = initializer.after_routes(router, &ctx).await.unwrap();
+
+        assert_eq!(status(&router, "/").await, StatusCode::OK);
+
+        mode.enable();
+        assert_eq!(status(&router, "/").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(&router, "/_health").await, StatusCode::OK);
+
+        mode.disable();
+        assert_eq!(status(&router, "/").await, StatusCode::OK);
+    }
+}
@@ -61,6 +61,8 @@
 //!````
 #[cfg(feature = "initializer-extra-db")]
 pub mod extra_db;
+#[cfg(feature = "initializer-maintenance")]
+pub mod maintenance;
 #[cfg(feature = "initializer-mongodb")]
 pub mod mongodb;
 #[cfg(feature = "initializer-multi-db")]
@@ -71,3 +73,9 @@ pub mod normalize_path;
 pub mod opentelemetry;
 #[cfg(feature = "initializer-prometheus")]
 pub mod prometheus;
+#[cfg(feature = "initializer-request-logging")]
+pub mod request_logging;
+#[cfg(feature = "initializer-routes-listing")]
+pub mod routes_listing;
+#[cfg(feature = "initializer-trace-context")]
+pub mod trace_context;
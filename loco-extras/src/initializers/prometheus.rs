@@ -1,9 +1,97 @@
+use std::time::Instant;
+
 use async_trait::async_trait;
-use axum::Router as AxumRouter;
-use axum_prometheus::PrometheusMetricLayer;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::{self, Next},
+    Router as AxumRouter,
+};
+use axum_prometheus::{PrometheusMetricLayer, PrometheusMetricLayerBuilder};
 use loco_rs::prelude::*;
+use serde::Deserialize;
+
+/// The `prometheus` initializer settings, read from the `initializers`
+/// config:
+///
+/// ```yaml
+/// initializers:
+///   prometheus:
+///     # Label request metrics with the HTTP method. Disable to lower the
+///     # metrics cardinality.
+///     method_label: true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrometheusConfig {
+    #[serde(default = "default_method_label")]
+    pub method_label: bool,
+}
+
+impl Default for PrometheusConfig {
+    fn default() -> Self {
+        Self {
+            method_label: default_method_label(),
+        }
+    }
+}
+
+fn default_method_label() -> bool {
+    true
+}
+
+#[derive(Default)]
+pub struct AxumPrometheusInitializer {
+    metric_prefix: Option<String>,
+}
 
-pub struct AxumPrometheusInitializer;
+impl AxumPrometheusInitializer {
+    /// Prefix the request metric names, e.g. `myapp_http_requests_total`
+    /// with the `myapp` prefix, so services scraped into a shared
+    /// Prometheus do not collide. Defaults to `axum`.
+    ///
+    /// The prefix is set once for the whole process, see
+    /// [`PrometheusMetricLayerBuilder::with_prefix`].
+    #[must_use]
+    pub fn with_metric_prefix(mut self, prefix: &str) -> Self {
+        self.metric_prefix = Some(prefix.to_string());
+        self
+    }
+}
+
+/// How [`track`] records the request metrics.
+#[derive(Clone)]
+struct Tracking {
+    requests_total: String,
+    requests_duration: String,
+}
+
+impl Tracking {
+    fn new(prefix: &str) -> Self {
+        Self {
+            requests_total: format!("{prefix}_http_requests_total"),
+            requests_duration: format!("{prefix}_http_requests_duration_seconds"),
+        }
+    }
+}
+
+/// Records the request metrics like the `axum-prometheus` layer does, without
+/// the `method` label.
+async fn track(State(tracking): State<Tracking>, request: Request, next: Next) -> Response {
+    let endpoint = request.extensions().get::<MatchedPath>().map_or_else(
+        || request.uri().path().to_string(),
+        |path| path.as_str().to_string(),
+    );
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    let labels = [
+        ("endpoint", endpoint),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!(tracking.requests_total.clone(), &labels).increment(1);
+    metrics::histogram!(tracking.requests_duration.clone(), &labels)
+        .record(started.elapsed().as_secs_f64());
+    response
+}
 
 #[async_trait]
 impl<T: Send + Sync + Clone> Initializer<T> for AxumPrometheusInitializer {
@@ -11,11 +99,157 @@ impl<T: Send + Sync + Clone> Initializer<T> for AxumPrometheusInitializer {
         "axum-prometheus".to_string()
     }
 
-    async fn after_routes(&self, router: AxumRouter, _ctx: &AppContext<T>) -> Result<AxumRouter> {
-        let (prometheus_layer, metric_handle) = PrometheusMetricLayer::pair();
-        let router = router
-            .route("/metrics", get(|| async move { metric_handle.render() }))
-            .layer(prometheus_layer);
+    async fn after_routes(&self, router: AxumRouter, ctx: &AppContext<T>) -> Result<AxumRouter> {
+        let config = match ctx
+            .config
+            .initializers
+            .as_ref()
+            .and_then(|initializers| initializers.get("prometheus"))
+        {
+            Some(value) => serde_json::from_value::<PrometheusConfig>(value.clone())?,
+            None => PrometheusConfig::default(),
+        };
+
+        let (prometheus_layer, metric_handle) = match &self.metric_prefix {
+            Some(prefix) => PrometheusMetricLayerBuilder::new()
+                .with_prefix(prefix.clone())
+                .with_default_metrics()
+                .build_pair(),
+            None => PrometheusMetricLayer::pair(),
+        };
+        let router = router.route("/metrics", get(|| async move { metric_handle.render() }));
+        let router = if config.method_label {
+            router.layer(prometheus_layer)
+        } else {
+            let tracking = Tracking::new(self.metric_prefix.as_deref().unwrap_or("axum"));
+            router.layer(middleware::from_fn_with_state(tracking, track))
+        };
         Ok(router)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use loco_rs::tests_cfg;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn send(router: &AxumRouter, method: &str, path: &str) -> String {
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(path)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    /// The request count series of `endpoint`, whatever the metric prefix:
+    /// the prefix is process wide, set by the first initializer having one.
+    fn request_series<'a>(metrics: &'a str, endpoint: &str) -> Vec<&'a str> {
+        let endpoint = format!("endpoint=\"{endpoint}\"");
+        metrics
+            .lines()
+            .filter(|line| line.contains("_http_requests_total{") && line.contains(&endpoint))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn can_label_requests_with_method() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let router = AxumRouter::new().route(
+            "/labeled",
+            get(|| async { "get" }).post(|| async { "post" }),
+        );
+        let router = AxumPrometheusInitializer::default()
+            .after_routes(router, &ctx)
+            .await
+            .unwrap();
+
+        send(&router, "GET", "/labeled").await;
+        send(&router, "POST", "/labeled").await;
+        send(&router, "POST", "/labeled").await;
+        let metrics = send(&router, "GET", "/metrics").await;
+
+        let series = request_series(&metrics, "/labeled");
+        assert_eq!(series.len(), 2);
+        assert!(series
+            .iter()
+            .any(|line| line.contains("method=\"GET\"") && line.ends_with(" 1")));
+        assert!(series
+            .iter()
+            .any(|line| line.contains("method=\"POST\"") && line.ends_with(" 2")));
+    }
+
+    #[tokio::test]
+    async fn can_disable_method_label() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.initializers = Some(
+            [(
+                "prometheus".to_string(),
+                serde_json::json!({ "method_label": false }),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let router = AxumRouter::new().route(
+            "/unlabeled",
+            get(|| async { "get" }).post(|| async { "post" }),
+        );
+        let router = AxumPrometheusInitializer::default()
+            .after_routes(router, &ctx)
+            .await
+            .unwrap();
+
+        send(&router, "GET", "/unlabeled").await;
+        send(&router, "POST", "/unlabeled").await;
+        let metrics = send(&router, "GET", "/metrics").await;
+
+        let series = request_series(&metrics, "/unlabeled");
+        assert_eq!(series.len(), 1);
+        assert!(!series[0].contains("method="));
+        assert!(series[0].ends_with(" 2"));
+    }
+
+    #[tokio::test]
+    async fn can_prefix_metric_names() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let router = AxumRouter::new().route(
+            "/prefixed",
+            get(|| async { "get" }).post(|| async { "post" }),
+        );
+        let router = AxumPrometheusInitializer::default()
+            .with_metric_prefix("myapp")
+            .after_routes(router, &ctx)
+            .await
+            .unwrap();
+
+        send(&router, "GET", "/prefixed").await;
+        send(&router, "POST", "/prefixed").await;
+        let metrics = send(&router, "GET", "/metrics").await;
+
+        let series = request_series(&metrics, "/prefixed");
+        assert_eq!(series.len(), 2);
+        assert!(series
+            .iter()
+            .all(|line| line.starts_with("myapp_http_requests_total{")));
+        assert!(series.iter().any(|line| line.contains("method=\"GET\"")));
+        assert!(series.iter().any(|line| line.contains("method=\"POST\"")));
+        assert!(metrics
+            .lines()
+            .any(|line| line.starts_with("myapp_http_requests_duration_seconds")));
+        assert!(metrics
+            .lines()
+            .any(|line| line.starts_with("myapp_http_requests_pending")));
+    }
+}
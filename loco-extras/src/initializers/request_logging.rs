@@ -0,0 +1,141 @@
+//! [Initializer] logging every request with its method, path, status and
+//! latency, as a `request_completed` event with the `http.method`,
+//! `http.path`, `http.status` and `latency_ms` fields.
+//!
+//! It layers the same middleware as the built-in `request_log` server
+//! middleware, for apps wiring their observability through initializers.
+//! Booting fails when `server.middlewares.request_log` is enabled too, as every
+//! request would be logged twice.
+//!
+//! The settings are read from the `initializers` config, with the same keys
+//! as the `request_log` middleware:
+//!
+//! ```yaml
+//! initializers:
+//!   request_logging:
+//!     fields: [method, path, status, latency]
+//!     level: debug
+//! ```
+use async_trait::async_trait;
+use axum::Router;
+use loco_rs::{config, controller::middleware::request_log::RequestLogLayer, prelude::*};
+
+#[allow(clippy::module_name_repetitions)]
+pub struct RequestLoggingInitializer;
+
+#[async_trait]
+impl<T: Send + Sync + Clone> Initializer<T> for RequestLoggingInitializer {
+    fn name(&self) -> String {
+        "request-logging".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, ctx: &AppContext<T>) -> Result<Router> {
+        if ctx
+            .config
+            .server
+            .middlewares
+            .request_log
+            .as_ref()
+            .is_some_and(|request_log| request_log.enable)
+        {
+            return Err(Error::Message(
+                "request-logging initializer: disable `server.middlewares.request_log`, it \
+                 already logs every request"
+                    .to_string(),
+            ));
+        }
+        let config = match ctx
+            .config
+            .initializers
+            .as_ref()
+            .and_then(|initializers| initializers.get("request_logging"))
+        {
+            Some(value) => serde_json::from_value(value.clone())?,
+            None => config::RequestLogMiddleware::default(),
+        };
+        Ok(router.layer(RequestLogLayer::new(config)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, extract::Request, routing::post};
+    use loco_rs::{tests_cfg, tests_cfg::capture::Capture};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn app(ctx: &AppContext<()>) -> Router {
+        let router = Router::new().route("/users/:id", post(|| async { "created" }));
+        RequestLoggingInitializer
+            .after_routes(router, ctx)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn can_log_requests() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let router = app(&ctx).await;
+
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+        router
+            .oneshot(Request::post("/users/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let events = capture.events_with_message("request_completed");
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.level, tracing::Level::INFO);
+        assert_eq!(event.field("http.method"), Some("POST"));
+        assert_eq!(event.field("http.path"), Some("/users/:id"));
+        assert_eq!(event.field("http.status"), Some("200"));
+        assert!(event.field("latency_ms").is_some());
+    }
+
+    #[tokio::test]
+    async fn refuses_to_log_requests_twice() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.server.middlewares.request_log = Some(config::RequestLogMiddleware::default());
+        let router = Router::new().route("/users/:id", post(|| async { "created" }));
+
+        let err = RequestLoggingInitializer
+            .after_routes(router, &ctx)
+            .await
+            .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("disable `server.middlewares.request_log`"));
+    }
+
+    #[tokio::test]
+    async fn can_configure_request_logging() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.initializers = Some(
+            [(
+                "request_logging".to_string(),
+                serde_json::json!({ "fields": ["method", "status"], "level": "debug" }),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let router = app(&ctx).await;
+
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+        router
+            .oneshot(Request::post("/users/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let events = capture.events_with_message("request_completed");
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.level, tracing::Level::DEBUG);
+        assert_eq!(event.field("http.method"), Some("POST"));
+        assert_eq!(event.field("http.path"), None);
+    }
+}
@@ -0,0 +1,183 @@
+//! [Initializer] serving a JSON listing of the application routes, a
+//! lightweight alternative to a full OpenAPI document for consumers and
+//! tooling:
+//!
+//! ```json
+//! [
+//!   { "method": "GET", "path": "/_ping" },
+//!   { "method": "POST", "path": "/api/users" }
+//! ]
+//! ```
+//!
+//! The initializer is created from the application routes:
+//!
+//! ```rust,ignore
+//! async fn initializers(ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
+//!     Ok(vec![Box::new(
+//!         loco_extras::initializers::routes_listing::RoutesListingInitializer::new(
+//!             &Self::routes(ctx),
+//!         ),
+//!     )])
+//! }
+//! ```
+//!
+//! The listing is served at `/_routes`, configurable in the `initializers`
+//! config:
+//!
+//! ```yaml
+//! initializers:
+//!   routes_listing:
+//!     path: /_routes
+//! ```
+use async_trait::async_trait;
+use axum::Router as AxumRouter;
+use loco_rs::{controller::AppRoutes, prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// The `routes_listing` initializer settings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoutesListingConfig {
+    #[serde(default = "default_path")]
+    pub path: String,
+}
+
+impl Default for RoutesListingConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+        }
+    }
+}
+
+fn default_path() -> String {
+    "/_routes".to_string()
+}
+
+/// A listed route.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RouteEntry {
+    pub method: String,
+    pub path: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct RoutesListingInitializer {
+    routes: Vec<RouteEntry>,
+}
+
+impl RoutesListingInitializer {
+    /// List the given application routes, by path then method.
+    #[must_use]
+    pub fn new<T: Send + Sync + Clone + 'static>(routes: &AppRoutes<T>) -> Self {
+        let mut routes = routes
+            .collect()
+            .into_iter()
+            .flat_map(|route| {
+                route.actions.into_iter().map(move |method| RouteEntry {
+                    method: method.to_string(),
+                    path: route.uri.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        routes.sort_by(|a, b| (&a.path, &a.method).cmp(&(&b.path, &b.method)));
+        Self { routes }
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone> Initializer<T> for RoutesListingInitializer {
+    fn name(&self) -> String {
+        "routes-listing".to_string()
+    }
+
+    async fn after_routes(&self, router: AxumRouter, ctx: &AppContext<T>) -> Result<AxumRouter> {
+        let config = match ctx
+            .config
+            .initializers
+            .as_ref()
+            .and_then(|initializers| initializers.get("routes_listing"))
+        {
+            Some(value) => serde_json::from_value::<RoutesListingConfig>(value.clone())?,
+            None => RoutesListingConfig::default(),
+        };
+
+        let routes = self.routes.clone();
+        Ok(router.route(
+            &config.path,
+            get(move || async move { Json(routes.clone()) }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        extract::Request,
+    };
+    use loco_rs::tests_cfg;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn handler() -> &'static str {
+        "loco"
+    }
+
+    fn app_routes() -> AppRoutes<()> {
+        AppRoutes::empty()
+            .add_route(Routes::new().add("/_ping", get(handler)))
+            .add_route(
+                Routes::new()
+                    .prefix("api/users")
+                    .add("/", post(handler))
+                    .add("/:id", delete(handler)),
+            )
+    }
+
+    async fn listing(router: AxumRouter, path: &str) -> serde_json::Value {
+        let response = router
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn listing_reflects_mounted_routes() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let router = RoutesListingInitializer::new(&app_routes())
+            .after_routes(AxumRouter::new(), &ctx)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            listing(router, "/_routes").await,
+            serde_json::json!([
+                { "method": "GET", "path": "/_ping" },
+                { "method": "POST", "path": "/api/users" },
+                { "method": "DELETE", "path": "/api/users/:id" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn can_configure_listing_path() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.initializers = Some(
+            [(
+                "routes_listing".to_string(),
+                serde_json::json!({ "path": "/api/_routes" }),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let router = RoutesListingInitializer::new(&app_routes())
+            .after_routes(AxumRouter::new(), &ctx)
+            .await
+            .unwrap();
+
+        assert_eq!(listing(router, "/api/_routes").await[0]["path"], "/_ping");
+    }
+}
@@ -0,0 +1,203 @@
+//! [Initializer] propagating the trace context, e.g. the
+//! [W3C trace context](https://www.w3.org/TR/trace-context/) `traceparent`
+//! and `tracestate` headers.
+//!
+//! The propagation is delegated to the global OpenTelemetry text map
+//! propagator, set up by the `opentelemetry` initializer (see the
+//! `OTEL_PROPAGATORS` environment variable, `tracecontext,baggage` by
+//! default), which also exports the spans:
+//!
+//! * incoming requests continue the caller trace: the context extracted from
+//!   the request headers is the parent of a `trace_context` span wrapping the
+//!   request, or a new trace is started
+//! * outgoing requests carry the current span context when their client is
+//!   wrapped with [`TraceContextLayer`], e.g. with a hyper client:
+//!
+//! ```rust,ignore
+//! let client = tower::ServiceBuilder::new()
+//!     .layer(TraceContextLayer)
+//!     .service(hyper_util::client::legacy::Client::builder(TokioExecutor::new()).build_http());
+//! ```
+//!
+//! The current trace id is available from
+//! [`tracing_opentelemetry_instrumentation_sdk::find_current_trace_id`].
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use axum::{
+    extract::Request,
+    http,
+    middleware::{self, Next},
+    Router,
+};
+use loco_rs::prelude::*;
+use tower::{Layer, Service};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_opentelemetry_instrumentation_sdk::{
+    find_current_context,
+    http::{extract_context, inject_context},
+};
+
+async fn propagate(request: Request, next: Next) -> Response {
+    let span = tracing::info_span!("trace_context");
+    span.set_parent(extract_context(request.headers()));
+    next.run(request).instrument(span).await
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct TraceContextInitializer;
+
+#[async_trait]
+impl<T: Send + Sync + Clone> Initializer<T> for TraceContextInitializer {
+    fn name(&self) -> String {
+        "trace-context".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, _ctx: &AppContext<T>) -> Result<Router> {
+        Ok(router.layer(middleware::from_fn(propagate)))
+    }
+}
+
+/// Wraps an HTTP client, injecting the current span context into the
+/// headers of outgoing requests with the global propagator.
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(clippy::module_name_repetitions)]
+pub struct TraceContextLayer;
+
+impl<S> Layer<S> for TraceContextLayer {
+    type Service = TraceContextService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceContextService { inner }
+    }
+}
+
+/// See [`TraceContextLayer`].
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct TraceContextService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for TraceContextService<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        inject_context(&find_current_context(), request.headers_mut());
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use axum::{body::Body, routing::get};
+    use loco_rs::tests_cfg;
+    use opentelemetry::trace::{TraceContextExt as _, TracerProvider as _};
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::TracerProvider};
+    use tower::ServiceExt;
+    use tracing_opentelemetry_instrumentation_sdk::find_current_trace_id;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    const TRACESTATE: &str = "vendor=opaque";
+
+    /// Exports the spans to OpenTelemetry, with the W3C propagator.
+    fn with_opentelemetry() -> tracing::subscriber::DefaultGuard {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = TracerProvider::builder().build().tracer("trace-context");
+        tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer)),
+        )
+    }
+
+    /// The headers an outgoing request gets from the current span.
+    fn outgoing_headers() -> http::HeaderMap {
+        let mut headers = http::HeaderMap::new();
+        inject_context(&find_current_context(), &mut headers);
+        headers
+    }
+
+    #[tokio::test]
+    async fn continues_the_incoming_trace() {
+        let _guard = with_opentelemetry();
+        let ctx = tests_cfg::app::get_app_context().await;
+        let router = Router::new().route(
+            "/",
+            get(|| async {
+                let headers = outgoing_headers();
+                assert_eq!(
+                    find_current_trace_id().as_deref(),
+                    Some("4bf92f3577b34da6a3ce929d0e0e4736")
+                );
+                assert_eq!(headers["tracestate"], TRACESTATE);
+                headers["traceparent"].to_str().unwrap().to_string()
+            }),
+        );
+        let router = TraceContextInitializer
+            .after_routes(router, &ctx)
+            .await
+            .unwrap();
+
+        let response = router
+            .oneshot(
+                Request::get("/")
+                    .header("traceparent", TRACEPARENT)
+                    .header("tracestate", TRACESTATE)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let outgoing = String::from_utf8(body.to_vec()).unwrap();
+        assert!(outgoing.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(!outgoing.contains("00f067aa0ba902b7"), "{outgoing}");
+    }
+
+    #[tokio::test]
+    async fn can_inject_trace_context_into_outgoing_requests() {
+        let _guard = with_opentelemetry();
+        let client =
+            TraceContextLayer.layer(tower::service_fn(|request: http::Request<()>| async move {
+                Ok::<_, Infallible>(
+                    request
+                        .headers()
+                        .get("traceparent")
+                        .map(|value| value.to_str().unwrap().to_string()),
+                )
+            }));
+
+        let sent = client
+            .clone()
+            .oneshot(http::Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(sent, None);
+
+        let span = tracing::info_span!("outgoing");
+        let trace_id = span.context().span().span_context().trace_id();
+        let sent = client
+            .oneshot(http::Request::new(()))
+            .instrument(span)
+            .await
+            .unwrap();
+        assert!(sent.unwrap().starts_with(&format!("00-{trace_id}-")));
+    }
+}
@@ -11,4 +11,13 @@
 //! * `initializer-multi-db` Adding extra DB's connection
 //! * `initializer-normalize-path` Normalize the request path
 //! * `initializer-opentelemetry` For adding opentelemetry tracing
+//! * `initializer-maintenance` Toggle a maintenance mode at runtime
+//! * `initializer-request-logging` Log requests with their status and latency
+//! * `initializer-trace-context` Propagate the W3C trace context
+//! * `initializer-routes-listing` Serve a JSON listing of the routes
+//!
+//! ### Tasks
+//! * `task-config-dump` Print the effective configuration, with secrets
+//!   redacted
 pub mod initializers;
+pub mod tasks;
@@ -0,0 +1,13 @@
+//! Tasks
+//!
+//! Reusable tasks, registered in the `register_tasks` hook:
+//!
+//! ```rust,ignore
+//! impl Hooks for App {
+//!     fn register_tasks(tasks: &mut Tasks) {
+//!         tasks.register(loco_extras::tasks::[MODULE_NAME]);
+//!     }
+//! }
+//! ```
+#[cfg(feature = "task-config-dump")]
+pub mod config_dump;
@@ -7,20 +7,23 @@ cfg_if::cfg_if! {
     } else {}
 
 }
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, RwLock};
 
 use async_trait::async_trait;
-use axum::Router as AxumRouter;
+use axum::{http::StatusCode, Router as AxumRouter};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
 
 #[cfg(feature = "channels")]
 use crate::controller::channels::AppChannels;
 use crate::{
-    boot::{BootResult, ServeParams, StartMode},
+    boot::{self, BootResult, ServeParams, StartMode},
     cache::{self},
     config::{self, Config},
-    controller::AppRoutes,
+    controller::{health::HealthRegistry, AppRoutes, ErrorDetail},
     environment::Environment,
     mailer::EmailSender,
+    server,
     storage::Storage,
     task::Tasks,
     worker::{Pool, Processor, RedisConnectionManager},
@@ -55,10 +58,30 @@ where
     pub storage: Arc<Storage>,
     // Cache instance for the application
     pub cache: Arc<cache::Cache>,
+    /// Cancelled when the application starts shutting down. Long running work
+    /// can listen on it to stop early.
+    pub cancellation_token: CancellationToken,
+    /// The health check components, read by the health and readiness
+    /// endpoints. Checks can be registered at runtime.
+    pub health: HealthRegistry<T>,
+    /// The active initializers in execution order, filled when the
+    /// application boots.
+    pub initializers: Arc<RwLock<Vec<InitializerInfo>>>,
 
     pub extra: Option<T>,
 }
 
+impl<T: Send + Sync + Clone> AppContext<T> {
+    /// List the active initializers in execution order.
+    #[must_use]
+    pub fn active_initializers(&self) -> Vec<InitializerInfo> {
+        self.initializers
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
 /// A trait that defines hooks for customizing and extending the behavior of a
 /// web server application.
 ///
@@ -66,7 +89,7 @@ where
 /// the application's routing, worker connections, task registration, and
 /// database actions according to their specific requirements and use cases.
 #[async_trait]
-pub trait Hooks: Send + Sync {
+pub trait Hooks: Send + Sync + 'static {
     type ExtraAppContext: Send + Sync + Clone + 'static;
 
     /// Defines the composite app version
@@ -115,20 +138,27 @@ pub trait Hooks: Send + Sync {
     ) -> Result<BootResult<Self::ExtraAppContext>>;
 
     /// Start serving the Axum web application on the specified address and
-    /// port.
+    /// port, or on the listener configured in `server.listener` (TLS or unix
+    /// domain socket), see [`server::bind`].
+    ///
+    /// The server shuts down gracefully on `SIGINT`/`SIGTERM`, see
+    /// [`boot::serve`].
     ///
     /// # Returns
     /// A Result indicating success () or an error if the server fails to start.
-    async fn serve(app: AxumRouter, server_config: ServeParams) -> Result<()> {
-        let listener = tokio::net::TcpListener::bind(&format!(
-            "{}:{}",
-            server_config.binding, server_config.port
-        ))
+    async fn serve(
+        app: AxumRouter,
+        ctx: &AppContext<Self::ExtraAppContext>,
+        server_config: ServeParams,
+    ) -> Result<()> {
+        let listener = server::bind(
+            &ctx.config.server.listener,
+            &server_config.binding,
+            server_config.port,
+        )
         .await?;
 
-        axum::serve(listener, app).await?;
-
-        Ok(())
+        boot::serve(listener, app, ctx).await
     }
 
     /// Override and return `Ok(true)` to provide an alternative logging and
@@ -155,6 +185,24 @@ pub trait Hooks: Send + Sync {
         Ok(router)
     }
 
+    /// Serialize the JSON body of handler errors, e.g. to match an existing
+    /// API's `{ "errors": [...] }` convention. Return `None` to keep the
+    /// default `{ "error": ..., "description": ... }` body.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// fn error_body(status: StatusCode, detail: &ErrorDetail) -> Option<serde_json::Value> {
+    ///     Some(serde_json::json!({
+    ///         "errors": [{ "status": status.as_u16(), "code": detail.error }]
+    ///     }))
+    /// }
+    /// ```
+    #[must_use]
+    fn error_body(_status: StatusCode, _detail: &ErrorDetail) -> Option<serde_json::Value> {
+        None
+    }
+
     /// Provide a list of initializers
     /// An initializer can be used to seamlessly add functionality to your app
     /// or to initialize some aspects of it.
@@ -205,6 +253,15 @@ pub trait Hooks: Send + Sync {
     async fn seed(db: &DatabaseConnection, path: &Path) -> Result<()>;
 }
 
+/// Describes an active initializer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InitializerInfo {
+    /// The initializer name
+    pub name: String,
+    /// The initializer priority
+    pub priority: i32,
+}
+
 /// An initializer.
 /// Initializers should be kept in `src/initializers/`
 #[async_trait]
@@ -213,6 +270,12 @@ pub trait Initializer<T: Send + Sync + Clone>: Sync + Send {
     /// The initializer name or identifier
     fn name(&self) -> String;
 
+    /// The initializer priority. Initializers run by ascending priority, and
+    /// by registration order for equal priorities.
+    fn priority(&self) -> i32 {
+        0
+    }
+
     /// Occurs after the app's `before_run`.
     /// Use this to for one-time initializations, load caches, perform web
     /// hooks, etc.
@@ -226,5 +289,12 @@ pub trait Initializer<T: Send + Sync + Clone>: Sync + Send {
     async fn after_routes(&self, router: AxumRouter, _ctx: &AppContext<T>) -> Result<AxumRouter> {
         Ok(router)
     }
+
+    /// Occurs after the server stopped serving requests, during a graceful
+    /// shutdown. The other initializers' hooks still run when this one fails.
+    /// Use this to flush buffers, close connections, etc.
+    async fn on_shutdown(&self, _app_context: &AppContext<T>) -> Result<()> {
+        Ok(())
+    }
 }
 // </snip>
@@ -1,6 +1,9 @@
 use colored::Colorize;
 
-use crate::boot::{BootResult, ServeParams};
+use crate::{
+    boot::{BootResult, ServeParams},
+    config,
+};
 
 pub const BANNER: &str = r"
                       ▄     ▀                     
@@ -72,11 +75,21 @@ pub fn print_banner<T: Send + Sync + Clone>(
     let mut servingline = Vec::new();
     if boot_result.router.is_some() {
         modes.push("server".green());
-        servingline.push(format!(
-            "listening on {}:{}",
-            server_config.binding.to_string().green(),
-            server_config.port.to_string().green()
-        ));
+        servingline.push(match &config.server.listener {
+            config::Listener::Tcp => format!(
+                "listening on {}:{}",
+                server_config.binding.green(),
+                server_config.port.to_string().green()
+            ),
+            config::Listener::Tls(_) => format!(
+                "listening on {}:{} (tls)",
+                server_config.binding.green(),
+                server_config.port.to_string().green()
+            ),
+            config::Listener::Unix(unix) => {
+                format!("listening on {}", unix.path.display().to_string().green())
+            }
+        });
     }
     if boot_result.processor.is_some() {
         modes.push("worker".green());
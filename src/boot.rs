@@ -1,25 +1,33 @@
 //! # Application Bootstrapping and Logic
 //! This module contains functions and structures for bootstrapping and running
 //! your application.
+use std::{
+    sync::{Arc, PoisonError},
+    time::Duration,
+};
+
 use axum::Router;
 #[cfg(feature = "with-db")]
 use sea_orm_migration::MigratorTrait;
-use tracing::{info, trace, warn};
+#[cfg(test)]
+use tokio::net::TcpListener;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{error, info, trace, warn};
 
 #[cfg(feature = "with-db")]
 use crate::db;
 use crate::{
-    app::{AppContext, Hooks},
+    app::{AppContext, Hooks, Initializer, InitializerInfo},
     banner::print_banner,
     cache,
     config::{self, Config},
-    controller::ListRoutes,
+    controller::{health::HealthRegistry, middleware::error_body, AppRoutes, ListRoutes},
     environment::Environment,
     errors::Error,
     mailer::{EmailSender, MailerWorker},
-    redis,
+    redis, server,
     storage::{self, Storage},
-    task::{self, Tasks},
+    task::{self, TaskWorker, Tasks},
     worker::{self, AppWorker, Pool, Processor, RedisConnectionManager, DEFAULT_QUEUES},
     Result,
 };
@@ -41,6 +49,16 @@ pub struct BootResult<T: Send + Sync + Clone> {
     pub router: Option<Router>,
     /// worker processor
     pub processor: Option<Processor>,
+    /// Application initializers, in execution order
+    pub initializers: Vec<Box<dyn Initializer<T>>>,
+}
+
+impl<T: Send + Sync + Clone> BootResult<T> {
+    /// List the active initializers in execution order.
+    #[must_use]
+    pub fn active_initializers(&self) -> Vec<InitializerInfo> {
+        self.app_context.active_initializers()
+    }
 }
 
 /// Configuration structure for serving an application.
@@ -70,34 +88,238 @@ pub async fn start<H: Hooks>(
     let BootResult {
         router,
         processor,
-        app_context: _,
+        app_context,
+        initializers,
     } = boot;
 
+    #[cfg(unix)]
+    tokio::spawn(reload_on_signal::<H>(app_context.clone()));
+
+    let jobs = TaskTracker::new();
+    let token = app_context.cancellation_token.clone();
     match (router, processor) {
         (Some(router), Some(processor)) => {
-            tokio::spawn(async move {
-                if let Err(err) = process(processor).await {
-                    tracing::error!("Error in processing: {:?}", err);
-                }
-            });
-            H::serve(router, server_config).await?;
+            tokio::spawn(process(processor, jobs.clone(), token));
+            H::serve(router, &app_context, server_config).await?;
         }
         (Some(router), None) => {
-            H::serve(router, server_config).await?;
+            H::serve(router, &app_context, server_config).await?;
         }
         (None, Some(processor)) => {
-            process(processor).await?;
+            tokio::spawn(wait_for_shutdown(token.clone()));
+            process(processor, jobs.clone(), token).await;
         }
         _ => {}
     }
+
+    shutdown(&app_context, &initializers, &jobs).await
+}
+
+/// Cancels the application, then waits for the jobs in flight and runs the
+/// initializers shutdown hooks, within `server.shutdown_grace_timeout`.
+///
+/// Every shutdown hook runs even when an earlier one fails; the failures are
+/// logged and returned together.
+async fn shutdown<T: Send + Sync + Clone>(
+    app_context: &AppContext<T>,
+    initializers: &[Box<dyn Initializer<T>>],
+    jobs: &TaskTracker,
+) -> Result<()> {
+    app_context.cancellation_token.cancel();
+    jobs.close();
+    let grace = Duration::from_millis(app_context.config.server.shutdown_grace_timeout);
+    shutdown_within(grace, async {
+        jobs.wait().await;
+        let mut failures = vec![];
+        for initializer in initializers {
+            if let Err(err) = initializer.on_shutdown(app_context).await {
+                let name = initializer.name();
+                error!(initializer = name, error = %err, "shutdown hook failed");
+                failures.push(format!("{name}: {err}"));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Message(format!(
+                "shutdown hooks failed: {}",
+                failures.join("; ")
+            )))
+        }
+    })
+    .await
+}
+
+/// Runs the remaining shutdown work, abandoning it when it does not finish
+/// within the grace period so the shutdown time stays bounded.
+async fn shutdown_within(
+    grace: Duration,
+    work: impl std::future::Future<Output = Result<()>>,
+) -> Result<()> {
+    tokio::time::timeout(grace, work).await.unwrap_or_else(|_| {
+        warn!(grace = ?grace, "shutdown grace timeout reached, abandoning pending work");
+        Ok(())
+    })
+}
+
+/// Serves the router on the given listener, with graceful shutdown.
+///
+/// On `SIGINT`/`SIGTERM` (or when the [`AppContext`] cancellation token is
+/// cancelled) the cancellation token is cancelled, which makes the readiness
+/// endpoint fail. The server keeps accepting connections for
+/// `server.shutdown_pre_drain_delay`, then stops accepting them. In-flight
+/// requests are then given `server.shutdown_timeout` to finish. A second
+/// signal forces an immediate exit.
+///
+/// # Errors
+///
+/// When the server fails
+pub async fn serve<T: Send + Sync + Clone>(
+    listener: impl Into<server::Listener>,
+    router: Router,
+    ctx: &AppContext<T>,
+) -> Result<()> {
+    let token = ctx.cancellation_token.clone();
+    let listening = CancellationToken::new();
+    let pre_drain_delay = Duration::from_millis(ctx.config.server.shutdown_pre_drain_delay);
+    let drain_timeout = Duration::from_millis(ctx.config.server.shutdown_timeout);
+
+    tokio::spawn(wait_for_shutdown(token.clone()));
+    let settings = server::ConnectionSettings::new(&ctx.config.server.connection);
+
+    tokio::select! {
+        () = server::serve(listener.into(), router, settings, listening.clone()) => {}
+        () = drain_deadline(token, listening, pre_drain_delay, drain_timeout) => {}
+    }
     Ok(())
 }
 
-async fn process(processor: Processor) -> Result<()> {
-    processor.run().await;
+/// Resolves when a shutdown is requested, by a signal or by cancelling the
+/// token.
+async fn wait_for_shutdown(token: CancellationToken) {
+    tokio::select! {
+        () = shutdown_signal() => {
+            info!("shutdown signal received, draining connections");
+            token.cancel();
+        }
+        () = token.cancelled() => {
+            info!("shutdown requested, draining connections");
+        }
+    }
+}
+
+/// Closes the listener once the pre-drain delay elapsed after a shutdown
+/// request, by cancelling `listening`, and resolves when draining should be
+/// cut short: either the drain timeout elapsed, or a second shutdown signal
+/// was received.
+async fn drain_deadline(
+    token: CancellationToken,
+    listening: CancellationToken,
+    pre_drain_delay: Duration,
+    timeout: Duration,
+) {
+    token.cancelled().await;
+    let drain = async {
+        if !pre_drain_delay.is_zero() {
+            info!(delay = ?pre_drain_delay, "readiness failing, closing the listener after the pre-drain delay");
+            tokio::time::sleep(pre_drain_delay).await;
+        }
+        listening.cancel();
+        tokio::time::sleep(timeout).await;
+    };
+    tokio::select! {
+        () = drain => {
+            warn!(timeout = ?timeout, "shutdown timeout reached, dropping in-flight requests");
+        }
+        () = shutdown_signal() => {
+            warn!("second shutdown signal received, forcing exit");
+        }
+    }
+}
+
+/// Resolves when the process receives `SIGINT` or `SIGTERM`.
+///
+/// # Panics
+///
+/// When the signal handlers could not be installed
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {},
+        () = terminate => {},
+    }
+}
+
+/// Re-reads the configuration of the environment and applies the settings that
+/// can change at runtime, without a restart: the logger level and filter.
+///
+/// Triggered by `SIGHUP`, or by an application admin endpoint. The reload
+/// generation and log level are reported in the `/_health` response.
+///
+/// # Errors
+///
+/// When the configuration could not be loaded or applied.
+pub fn reload_config<H: Hooks>(app_context: &AppContext<H::ExtraAppContext>) -> Result<()> {
+    let config = app_context.environment.load()?;
+    crate::logger::reload::<H>(&config.logger)?;
+    app_context
+        .health
+        .record_config_reload(&config.logger.level.to_string());
     Ok(())
 }
 
+/// Reloads the configuration on every `SIGHUP`, until the token is
+/// cancelled.
+#[cfg(unix)]
+async fn reload_on_signal<H: Hooks>(app_context: AppContext<H::ExtraAppContext>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            warn!(error = %err, "could not install the config reload signal handler");
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            () = app_context.cancellation_token.cancelled() => return,
+            _ = hangup.recv() => {
+                info!("reload signal received, reloading the configuration");
+                if let Err(err) = reload_config::<H>(&app_context) {
+                    warn!(error = %err, "could not reload the configuration");
+                }
+            }
+        }
+    }
+}
+
+/// Runs the worker processor until the token is cancelled. The jobs in
+/// flight are tracked in `jobs`, for the shutdown to wait for them.
+async fn process(mut processor: Processor, jobs: TaskTracker, token: CancellationToken) {
+    processor.using(worker::TrackJobs(jobs)).await;
+    tokio::select! {
+        () = processor.run() => {}
+        () = token.cancelled() => info!("shutdown requested, stopping the workers"),
+    }
+}
+
 /// Run task
 ///
 /// # Errors
@@ -112,8 +334,6 @@ pub async fn run_task<H: Hooks>(
     H::register_tasks(&mut tasks);
 
     if let Some(task) = task {
-        let task_span = tracing::span!(tracing::Level::DEBUG, "task", task,);
-        let _guard = task_span.enter();
         tasks.run(app_context, task, vars).await?;
     } else {
         let list = tasks.list();
@@ -213,6 +433,9 @@ pub async fn create_context<H: Hooks>(
         queue: connect_redis(&config).await,
         storage: Storage::single(storage::drivers::null::new()).into(),
         cache: cache::Cache::new(cache::drivers::null::new()).into(),
+        cancellation_token: CancellationToken::new(),
+        health: HealthRegistry::from_config(&config.server.health)?,
+        initializers: Arc::default(),
         config,
         mailer,
         extra: None,
@@ -264,50 +487,106 @@ pub async fn run_app<H: Hooks>(
     app_context: AppContext<H::ExtraAppContext>,
 ) -> Result<BootResult<H::ExtraAppContext>> {
     H::before_run(&app_context).await?;
-    let initializers = H::initializers(&app_context).await?;
+    let mut initializers = H::initializers(&app_context).await?;
+    // stable sort, keeps the registration order for equal priorities
+    initializers.sort_by_key(|initializer| initializer.priority());
     info!(initializers = ?initializers.iter().map(|init| init.name()).collect::<Vec<_>>().join(","), "initializers loaded");
+    *app_context
+        .initializers
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = initializers
+        .iter()
+        .map(|initializer| InitializerInfo {
+            name: initializer.name(),
+            priority: initializer.priority(),
+        })
+        .collect();
     for initializer in &initializers {
         initializer.before_run(&app_context).await?;
     }
+    let mut tasks = Tasks::default();
+    H::register_tasks(&mut tasks);
     match mode {
         StartMode::ServerOnly => {
-            let app = H::routes(&app_context).to_router(app_context.clone())?;
+            let app_routes = H::routes(&app_context);
+            warn_if_unwired(&tasks, Some(&app_routes));
+            let app = app_routes.to_router(app_context.clone())?;
             let mut router = H::after_routes(app, &app_context).await?;
             for initializer in &initializers {
                 router = initializer.after_routes(router, &app_context).await?;
             }
+            let router = error_body::layer(router, H::error_body);
 
             Ok(BootResult {
                 app_context,
                 router: Some(router),
                 processor: None,
+                initializers,
             })
         }
         StartMode::ServerAndWorker => {
-            let processor = create_processor::<H>(&app_context)?;
-            let app = H::routes(&app_context).to_router(app_context.clone())?;
+            let app_routes = H::routes(&app_context);
+            warn_if_unwired(&tasks, Some(&app_routes));
+            let processor = create_processor::<H>(&app_context, tasks)?;
+            let app = app_routes.to_router(app_context.clone())?;
             let mut router = H::after_routes(app, &app_context).await?;
             for initializer in &initializers {
                 router = initializer.after_routes(router, &app_context).await?;
             }
+            let router = error_body::layer(router, H::error_body);
             Ok(BootResult {
                 app_context,
                 router: Some(router),
                 processor: Some(processor),
+                initializers,
             })
         }
         StartMode::WorkerOnly => {
-            let processor = create_processor::<H>(&app_context)?;
+            warn_if_unwired(&tasks, None);
+            let processor = create_processor::<H>(&app_context, tasks)?;
             Ok(BootResult {
                 app_context,
                 router: None,
                 processor: Some(processor),
+                initializers,
             })
         }
     }
 }
+
+/// Warn when the app registers no tasks, or only the built-in routes when
+/// serving, which almost always means a wiring bug rather than an
+/// intentionally empty app.
+fn warn_if_unwired<T: Send + Sync + Clone + 'static>(
+    tasks: &Tasks<T>,
+    routes: Option<&AppRoutes<T>>,
+) {
+    if tasks.list().is_empty() {
+        warn!("no tasks are registered, check `Hooks::register_tasks`");
+    }
+
+    let Some(routes) = routes else {
+        return;
+    };
+    let builtin = AppRoutes::<T>::with_default_routes()
+        .collect()
+        .into_iter()
+        .map(|route| route.uri)
+        .collect::<Vec<_>>();
+    let unwired = routes
+        .collect()
+        .iter()
+        .all(|route| builtin.iter().any(|uri| route.uri.ends_with(uri.as_str())));
+    if unwired {
+        warn!("no routes are registered, every request will be answered with a 404, check `Hooks::routes`");
+    }
+}
+
 /// Creates and configures a [`Processor`] for handling worker tasks.
-fn create_processor<H: Hooks>(app_context: &AppContext<H::ExtraAppContext>) -> Result<Processor> {
+fn create_processor<H: Hooks>(
+    app_context: &AppContext<H::ExtraAppContext>,
+    tasks: Tasks<H::ExtraAppContext>,
+) -> Result<Processor> {
     let queues = worker::get_queues(&app_context.config.workers.queues);
     trace!(
         queues = ?queues,
@@ -328,6 +607,7 @@ fn create_processor<H: Hooks>(app_context: &AppContext<H::ExtraAppContext>) -> R
     };
 
     p.register(MailerWorker::build(app_context));
+    p.register(TaskWorker::<H>::new(app_context, Arc::new(tasks)));
     H::connect_workers(&mut p, app_context);
 
     trace!("done registering workers and queues");
@@ -369,3 +649,388 @@ pub async fn connect_redis(config: &Config) -> Option<Pool<RedisConnectionManage
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use axum::routing::get;
+
+    use super::*;
+    use crate::{tests_cfg, tests_cfg::capture::Capture};
+
+    /// Requests a slow route, requests a shutdown while the request is in
+    /// flight, and returns the raw response.
+    fn request_slow_route_during_shutdown(shutdown_timeout: u64) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = CancellationToken::new();
+        let server_token = token.clone();
+
+        // the server runs in its own runtime, dropped when `serve` returns, like
+        // a process exiting.
+        let server = std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            rt.block_on(async move {
+                let mut ctx = tests_cfg::app::get_app_context().await;
+                ctx.config.server.shutdown_timeout = shutdown_timeout;
+                ctx.cancellation_token = server_token;
+                let router = Router::new().route(
+                    "/slow",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_millis(300)).await;
+                        "done"
+                    }),
+                );
+                serve(TcpListener::from_std(listener).unwrap(), router, &ctx)
+                    .await
+                    .unwrap();
+            });
+        });
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        token.cancel();
+
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        server.join().unwrap();
+        response
+    }
+
+    struct NamedInitializer(&'static str, i32);
+
+    #[async_trait::async_trait]
+    impl Initializer<()> for NamedInitializer {
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn priority(&self) -> i32 {
+            self.1
+        }
+    }
+
+    struct TestApp;
+
+    #[async_trait::async_trait]
+    impl Hooks for TestApp {
+        type ExtraAppContext = ();
+
+        fn app_name() -> &'static str {
+            "test"
+        }
+
+        async fn boot(
+            mode: StartMode,
+            _environment: &Environment,
+        ) -> Result<BootResult<Self::ExtraAppContext>> {
+            run_app::<Self>(&mode, tests_cfg::app::get_app_context().await).await
+        }
+
+        async fn initializers(
+            _ctx: &AppContext<Self::ExtraAppContext>,
+        ) -> Result<Vec<Box<dyn Initializer<Self::ExtraAppContext>>>> {
+            Ok(vec![
+                Box::new(NamedInitializer("views", 0)),
+                Box::new(NamedInitializer("metrics", 10)),
+                Box::new(NamedInitializer("db", -10)),
+                Box::new(NamedInitializer("auth", 0)),
+            ])
+        }
+
+        fn routes(
+            _ctx: &AppContext<Self::ExtraAppContext>,
+        ) -> crate::controller::AppRoutes<Self::ExtraAppContext> {
+            crate::controller::AppRoutes::empty()
+        }
+
+        #[cfg(feature = "channels")]
+        fn register_channels(
+            _ctx: &AppContext<Self::ExtraAppContext>,
+        ) -> crate::controller::channels::AppChannels {
+            crate::controller::channels::AppChannels::default()
+        }
+
+        fn connect_workers<'a>(_p: &'a mut Processor, _ctx: &'a AppContext<Self::ExtraAppContext>) {
+        }
+
+        fn register_tasks(_tasks: &mut Tasks<Self::ExtraAppContext>) {}
+
+        #[cfg(feature = "with-db")]
+        async fn truncate(_db: &sea_orm::DatabaseConnection) -> Result<()> {
+            Ok(())
+        }
+
+        #[cfg(feature = "with-db")]
+        async fn seed(_db: &sea_orm::DatabaseConnection, _path: &std::path::Path) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn can_list_active_initializers_in_priority_order() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        // handed to routes and tasks before the application boots
+        let shared = ctx.clone();
+        assert!(shared.active_initializers().is_empty());
+
+        let boot = run_app::<TestApp>(&StartMode::ServerOnly, ctx)
+            .await
+            .unwrap();
+
+        assert_eq!(shared.active_initializers(), boot.active_initializers());
+        assert_eq!(
+            boot.active_initializers(),
+            vec![
+                InitializerInfo {
+                    name: "db".to_string(),
+                    priority: -10
+                },
+                InitializerInfo {
+                    name: "views".to_string(),
+                    priority: 0
+                },
+                InitializerInfo {
+                    name: "auth".to_string(),
+                    priority: 0
+                },
+                InitializerInfo {
+                    name: "metrics".to_string(),
+                    priority: 10
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn can_boot_the_test_app() {
+        let boot = TestApp::boot(StartMode::ServerOnly, &Environment::Test)
+            .await
+            .unwrap();
+
+        assert!(boot.router.is_some());
+        assert_eq!(boot.app_context.active_initializers().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn can_enable_json_fallback() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.server.middlewares.fallback = Some(config::EnableMiddleware { enable: true });
+        let boot = run_app::<TestApp>(&StartMode::ServerOnly, ctx)
+            .await
+            .unwrap();
+        let server = axum_test::TestServer::new(boot.router.unwrap()).unwrap();
+
+        let res = server.get("/unknown").await;
+
+        res.assert_status_not_found();
+        res.assert_json(&serde_json::json!({
+            "error": "not_found",
+            "description": "Resource was not found",
+        }));
+        assert!(res.headers().contains_key("x-request-id"));
+    }
+
+    #[tokio::test]
+    async fn warns_when_no_tasks_or_routes_are_registered() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let capture = Capture::default();
+        {
+            let _guard = capture.set_default();
+            run_app::<TestApp>(&StartMode::ServerOnly, ctx)
+                .await
+                .unwrap();
+        }
+
+        let warnings = capture
+            .events()
+            .into_iter()
+            .filter(|event| event.level == tracing::Level::WARN)
+            .filter_map(|event| event.message().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        assert!(warnings
+            .iter()
+            .any(|message| message.starts_with("no tasks are registered")));
+        assert!(warnings
+            .iter()
+            .any(|message| message.starts_with("no routes are registered")));
+    }
+
+    #[test]
+    fn builtin_routes_alone_are_unwired() {
+        let route_warnings = |routes: &AppRoutes<()>| {
+            let capture = Capture::default();
+            let _guard = capture.set_default();
+            warn_if_unwired(&Tasks::<()>::default(), Some(routes));
+            capture
+                .events()
+                .iter()
+                .filter(|event| {
+                    event
+                        .message()
+                        .is_some_and(|message| message.starts_with("no routes are registered"))
+                })
+                .count()
+        };
+
+        assert_eq!(route_warnings(&AppRoutes::with_default_routes()), 1);
+        assert_eq!(
+            route_warnings(&AppRoutes::with_default_routes().prefix("api")),
+            1
+        );
+        assert_eq!(
+            route_warnings(&AppRoutes::with_default_routes().add_route(
+                crate::controller::Routes::new().add("/users", axum::routing::get(|| async { "" }))
+            )),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn shutdown_completes_within_grace_period_with_stuck_job() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.server.shutdown_grace_timeout = 50;
+        let jobs = TaskTracker::new();
+        jobs.spawn(std::future::pending::<()>());
+        let started = std::time::Instant::now();
+
+        let res = tokio::time::timeout(Duration::from_secs(5), shutdown(&ctx, &[], &jobs)).await;
+
+        assert!(matches!(res, Ok(Ok(()))));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(ctx.cancellation_token.is_cancelled());
+    }
+
+    struct ShutdownHook {
+        name: &'static str,
+        fails: bool,
+        ran: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl Initializer<()> for ShutdownHook {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        async fn on_shutdown(&self, _app_context: &AppContext<()>) -> Result<()> {
+            self.ran.store(true, std::sync::atomic::Ordering::SeqCst);
+            if self.fails {
+                return Err(Error::string("flush failed"));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_runs_every_hook_and_reports_the_failures() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let hooks = [("first", true), ("second", false), ("third", true)].map(|(name, fails)| {
+            ShutdownHook {
+                name,
+                fails,
+                ran: Arc::default(),
+            }
+        });
+        let ran = hooks
+            .iter()
+            .map(|hook| hook.ran.clone())
+            .collect::<Vec<_>>();
+        let initializers = hooks
+            .into_iter()
+            .map(|hook| Box::new(hook) as Box<dyn Initializer<()>>)
+            .collect::<Vec<_>>();
+
+        let err = shutdown(&ctx, &initializers, &TaskTracker::new())
+            .await
+            .unwrap_err();
+
+        assert!(ran
+            .iter()
+            .all(|ran| ran.load(std::sync::atomic::Ordering::SeqCst)));
+        assert_eq!(
+            err.to_string(),
+            "shutdown hooks failed: first: flush failed; third: flush failed"
+        );
+    }
+
+    #[tokio::test]
+    async fn worker_only_mode_stops_on_shutdown() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.server.shutdown_grace_timeout = 50;
+        let token = ctx.cancellation_token.clone();
+        // never connects, the workers wait for a connection
+        let queue = Pool::builder()
+            .build_unchecked(RedisConnectionManager::new("redis://127.0.0.1:1").unwrap());
+        let boot = BootResult {
+            app_context: ctx,
+            router: None,
+            processor: Some(Processor::new(queue, vec!["default".to_string()])),
+            initializers: vec![],
+        };
+        let params = ServeParams {
+            port: 0,
+            binding: "localhost".to_string(),
+        };
+
+        let run = tokio::spawn(start::<TestApp>(boot, params));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        token.cancel();
+
+        let res = tokio::time::timeout(Duration::from_secs(5), run).await;
+        assert!(matches!(res, Ok(Ok(Ok(())))));
+    }
+
+    #[tokio::test]
+    async fn accepts_connections_during_the_pre_drain_delay() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.server.shutdown_pre_drain_delay = 300;
+        let token = ctx.cancellation_token.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route("/", get(|| async { "ok" }));
+        let server = tokio::spawn(async move { serve(listener, router, &ctx).await });
+
+        token.cancel();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+
+        server.await.unwrap().unwrap();
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[test]
+    fn in_flight_request_completes_within_shutdown_timeout() {
+        let response = request_slow_route_during_shutdown(2_000);
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("done"));
+    }
+
+    #[test]
+    fn in_flight_request_is_cut_after_shutdown_timeout() {
+        let response = request_slow_route_during_shutdown(50);
+
+        assert!(!response.contains("done"));
+    }
+}
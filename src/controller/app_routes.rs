@@ -2,9 +2,15 @@
 //! configuring routes in an Axum application. It allows you to define route
 //! prefixes, add routes, and configure middlewares for the application.
 
-use std::{fmt, path::PathBuf, time::Duration};
-
-use axum::{http, response::IntoResponse, Router as AXRouter};
+use std::{collections::HashSet, fmt, path::PathBuf, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Request, State},
+    http,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router as AXRouter,
+};
 use lazy_static::lazy_static;
 use regex::Regex;
 use tower_http::{
@@ -22,7 +28,15 @@ use tower_http::{
 use super::channels::AppChannels;
 use super::routes::Routes;
 use crate::{
-    app::AppContext, config, controller::middleware::etag::EtagLayer, environment::Environment,
+    app::AppContext,
+    config,
+    controller::middleware::{
+        etag::EtagLayer,
+        fallback,
+        request_id::{self, RequestId},
+        request_log::RequestLogLayer,
+    },
+    environment::Environment,
     errors, Result,
 };
 
@@ -66,11 +80,9 @@ impl<T: Send + Sync + Clone + 'static> AppRoutes<T> {
     /// Create a new instance with the default routes.
     #[must_use]
     pub fn with_default_routes() -> Self {
-        let routes = Self::empty().add_route(super::ping::routes());
-        #[cfg(feature = "with-db")]
-        let routes = routes.add_route(super::health::routes());
-
-        routes
+        Self::empty()
+            .add_route(super::ping::routes())
+            .add_route(super::health::routes())
     }
 
     /// Create an empty instance.
@@ -171,14 +183,37 @@ impl<T: Send + Sync + Clone + 'static> AppRoutes<T> {
         self
     }
 
+    /// Check that no two handlers are registered on the same method and
+    /// path, e.g. by two modules mounting the same route, which would
+    /// otherwise shadow each other.
+    ///
+    /// # Errors
+    /// Return an [`Result`] naming the first conflicting method and path.
+    pub fn validate_unique(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for route in self.collect() {
+            for action in &route.actions {
+                if !seen.insert((action.clone(), route.uri.clone())) {
+                    return Err(errors::Error::Message(format!(
+                        "duplicate route: [{action}] {} is registered more than once",
+                        route.uri
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Convert the routes to an Axum Router, and set a list of middlewares that
     /// configure in the [`config::Config`]
     ///
     /// # Errors
     /// Return an [`Result`] when could not convert the router setup to
-    /// [`axum::Router`].
+    /// [`axum::Router`], or when a method and path are registered more than
+    /// once (see [`AppRoutes::validate_unique`]).
     #[allow(clippy::cognitive_complexity)]
     pub fn to_router(&self, ctx: AppContext<T>) -> Result<AXRouter> {
+        self.validate_unique()?;
         let mut app = AXRouter::new();
 
         for router in self.collect() {
@@ -187,6 +222,13 @@ impl<T: Send + Sync + Clone + 'static> AppRoutes<T> {
             app = app.route(&router.uri, router.method);
         }
 
+        if let Some(fallback) = &ctx.config.server.middlewares.fallback {
+            if fallback.enable {
+                tracing::info!("[Middleware] Adding JSON fallback");
+                app = fallback::layer(app);
+            }
+        }
+
         app = Self::add_powered_by_header(app, &ctx.config.server);
 
         if let Some(catch_panic) = &ctx.config.server.middlewares.catch_panic {
@@ -207,12 +249,20 @@ impl<T: Send + Sync + Clone + 'static> AppRoutes<T> {
             }
         }
 
+        if let Some(request_log) = &ctx.config.server.middlewares.request_log {
+            if request_log.enable {
+                app = Self::add_request_log_middleware(app, request_log.clone());
+            }
+        }
+
         if let Some(logger) = &ctx.config.server.middlewares.logger {
             if logger.enable {
                 app = Self::add_logger_middleware(app, &ctx.environment);
             }
         }
 
+        app = app.layer(axum::middleware::from_fn(request_id::assign_request_id));
+
         if let Some(timeout_request) = &ctx.config.server.middlewares.timeout_request {
             if timeout_request.enable {
                 app = Self::add_timeout_middleware(app, timeout_request);
@@ -374,7 +424,11 @@ impl<T: Send + Sync + Clone + 'static> AppRoutes<T> {
         let app = app
             .layer(
                 TraceLayer::new_for_http().make_span_with(|request: &http::Request<_>| {
-                    let request_id = uuid::Uuid::new_v4();
+                    let request_id = request
+                        .extensions()
+                        .get::<RequestId>()
+                        .cloned()
+                        .unwrap_or_default();
                     let user_agent = request
                         .headers()
                         .get(axum::http::header::USER_AGENT)
@@ -403,11 +457,27 @@ impl<T: Send + Sync + Clone + 'static> AppRoutes<T> {
         app
     }
 
+    fn add_request_log_middleware(
+        app: AXRouter<AppContext<T>>,
+        config: config::RequestLogMiddleware,
+    ) -> AXRouter<AppContext<T>> {
+        let app = app.layer(RequestLogLayer::new(config));
+        tracing::info!("[Middleware] Adding request log");
+        app
+    }
+
     fn add_timeout_middleware(
         app: AXRouter<AppContext<T>>,
         config: &config::TimeoutRequestMiddleware,
     ) -> AXRouter<AppContext<T>> {
-        let app = app.layer(TimeoutLayer::new(Duration::from_millis(config.timeout)));
+        let app = if config.overrides.is_empty() {
+            app.layer(TimeoutLayer::new(Duration::from_millis(config.timeout)))
+        } else {
+            app.layer(axum::middleware::from_fn_with_state(
+                Arc::new(RouteTimeouts::new(config)),
+                route_timeout,
+            ))
+        };
 
         tracing::info!("[Middleware] Adding timeout layer");
         app
@@ -461,3 +531,149 @@ fn handle_panic(err: Box<dyn std::any::Any + Send + 'static>) -> axum::response:
 
     errors::Error::InternalServerError.into_response()
 }
+
+/// The request timeouts by path prefix, see
+/// [`config::TimeoutRequestMiddleware::overrides`].
+struct RouteTimeouts {
+    default: Duration,
+    /// Longest prefixes first
+    overrides: Vec<(String, Duration)>,
+}
+
+impl RouteTimeouts {
+    fn new(config: &config::TimeoutRequestMiddleware) -> Self {
+        let mut overrides = config
+            .overrides
+            .iter()
+            .map(|(prefix, timeout)| {
+                let prefix = prefix.trim_end_matches('*').trim_end_matches('/');
+                (prefix.to_string(), Duration::from_millis(*timeout))
+            })
+            .collect::<Vec<_>>();
+        overrides.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Self {
+            default: Duration::from_millis(config.timeout),
+            overrides,
+        }
+    }
+
+    fn timeout_for(&self, path: &str) -> Duration {
+        self.overrides
+            .iter()
+            .find(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .map_or(self.default, |(_, timeout)| *timeout)
+    }
+}
+
+/// Time out the request after the timeout of its path, answering with a
+/// `408 Request Timeout` like [`TimeoutLayer`].
+async fn route_timeout(
+    State(timeouts): State<Arc<RouteTimeouts>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let timeout = timeouts.timeout_for(request.uri().path());
+    tokio::time::timeout(timeout, next.run(request))
+        .await
+        .unwrap_or_else(|_| http::StatusCode::REQUEST_TIMEOUT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::{get, post};
+    use axum_test::TestServer;
+
+    use super::*;
+    use crate::tests_cfg;
+
+    async fn handler() -> &'static str {
+        "loco"
+    }
+
+    #[test]
+    fn default_routes_include_health() {
+        let uris = AppRoutes::<()>::with_default_routes()
+            .collect()
+            .into_iter()
+            .map(|route| route.uri)
+            .collect::<Vec<_>>();
+
+        assert_eq!(uris, vec!["/_ping", "/_readiness", "/_health"]);
+    }
+
+    #[test]
+    fn unique_routes_are_valid() {
+        let routes = AppRoutes::<()>::empty()
+            .add_route(Routes::new().add("/users", get(handler)))
+            .add_route(Routes::new().add("/users", post(handler)))
+            .add_route(Routes::at("admin").add("/users", get(handler)));
+
+        assert!(routes.validate_unique().is_ok());
+    }
+
+    #[test]
+    fn can_detect_duplicate_routes() {
+        let routes = AppRoutes::<()>::empty()
+            .add_route(Routes::at("api").add("/users", get(handler)))
+            .add_route(Routes::new().add("/api/users", get(handler)));
+
+        assert!(matches!(
+            routes.validate_unique(),
+            Err(errors::Error::Message(msg))
+                if msg == "duplicate route: [GET] /api/users is registered more than once"
+        ));
+    }
+
+    #[tokio::test]
+    async fn request_log_is_off_unless_configured() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        let added = |ctx: &AppContext<()>| {
+            let capture = tests_cfg::capture::Capture::default();
+            let _guard = capture.set_default();
+            let _router = AppRoutes::<()>::empty().to_router(ctx.clone()).unwrap();
+            !capture
+                .events_with_message("[Middleware] Adding request log")
+                .is_empty()
+        };
+
+        ctx.config.server.middlewares.request_log = None;
+        assert!(!added(&ctx));
+
+        ctx.config.server.middlewares.request_log = Some(config::RequestLogMiddleware::default());
+        assert!(added(&ctx));
+    }
+
+    async fn slow_handler() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        "done"
+    }
+
+    #[tokio::test]
+    async fn timeout_overrides_apply_by_path_prefix() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let config = config::TimeoutRequestMiddleware {
+            enable: true,
+            timeout: 20,
+            overrides: [("/reports/*".to_string(), 5_000)].into_iter().collect(),
+        };
+        let app = AXRouter::new()
+            .route("/reports/daily", get(slow_handler))
+            .route("/reportsfoo", get(slow_handler))
+            .route("/users", get(slow_handler));
+        let app = AppRoutes::<()>::add_timeout_middleware(app, &config).with_state(ctx);
+        let server = TestServer::new(app).unwrap();
+
+        server.get("/reports/daily").await.assert_status_ok();
+        server
+            .get("/users")
+            .await
+            .assert_status(http::StatusCode::REQUEST_TIMEOUT);
+        server
+            .get("/reportsfoo")
+            .await
+            .assert_status(http::StatusCode::REQUEST_TIMEOUT);
+    }
+}
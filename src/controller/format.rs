@@ -21,19 +21,28 @@
 //! }
 //! ```
 
+use std::path::Path;
+
 use axum::{
     body::Body,
-    http::{response::Builder, HeaderName, HeaderValue},
+    http::{response::Builder, HeaderName, HeaderValue, Uri},
     response::{Html, IntoResponse, Redirect, Response},
 };
 use axum_extra::extract::cookie::Cookie;
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use hyper::{header, StatusCode};
 use serde::Serialize;
 use serde_json::json;
+use tokio_util::io::ReaderStream;
 
 use super::views::ViewRenderer;
-use crate::{controller::Json, Result};
+use crate::{
+    controller::{
+        pagination::{CursorPaginated, Pagination},
+        Json,
+    },
+    Error, Result,
+};
 
 /// Returns an empty response.
 ///
@@ -106,6 +115,209 @@ pub fn json<T: Serialize>(t: T) -> Result<Response> {
     Ok(Json(t).into_response())
 }
 
+/// Returns a JSON response from an already built [`serde_json::Value`],
+/// without converting it through a typed struct.
+///
+/// # Example:
+///
+/// ```rust
+/// use loco_rs::prelude::*;
+///
+/// async fn endpoint() -> Result<Response> {
+///    format::json_value(serde_json::json!({ "ok": true }))
+/// }
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if serde fails
+pub fn json_value(value: serde_json::Value) -> Result<Response> {
+    json(value)
+}
+
+/// Returns a JSON response from pre-serialized bytes. The body is passed
+/// through as-is, so it is up to the caller to make sure it is valid JSON.
+///
+/// # Example:
+///
+/// ```rust
+/// use loco_rs::prelude::*;
+///
+/// async fn endpoint() -> Result<Response> {
+///    format::json_raw(r#"{"ok":true}"#)
+/// }
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if the response could not be built
+pub fn json_raw<B: Into<Bytes>>(bytes: B) -> Result<Response> {
+    Ok(Builder::new()
+        .status(StatusCode::OK)
+        .header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+        )
+        .body(Body::from(bytes.into()))?)
+}
+
+/// Returns a JSON response with a page of results and its pagination details.
+///
+/// # Example:
+///
+/// ```rust
+/// use loco_rs::prelude::*;
+///
+/// async fn list(pagination: Pagination) -> Result<Response> {
+///    let items = vec!["loco"];
+///    format::paginated(items, &pagination, 1)
+/// }
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if serde fails
+pub fn paginated<T: Serialize>(
+    results: Vec<T>,
+    pagination: &Pagination,
+    total_items: u64,
+) -> Result<Response> {
+    json(pagination.paginated(results, total_items))
+}
+
+/// Returns a JSON response with a page of results and the opaque cursor of the
+/// next page, for cursor based pagination.
+///
+/// When there is a next page the cursor is also advertised in a
+/// `Link: <...?cursor=...>; rel="next"` header: the requested `uri` (see
+/// [`axum::extract::OriginalUri`]) with its `cursor` query parameter set,
+/// keeping the other parameters. On the last page pass `None`, the
+/// `next_cursor` field and the header are omitted.
+///
+/// # Example:
+///
+/// ```rust
+/// use axum::extract::OriginalUri;
+/// use loco_rs::prelude::*;
+///
+/// async fn list(OriginalUri(uri): OriginalUri) -> Result<Response> {
+///    let items = vec!["loco"];
+///    format::cursor_paginated(&uri, items, Some("eyJpZCI6NDJ9"))
+/// }
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if serde fails or the response could
+/// not be built
+pub fn cursor_paginated<T: Serialize>(
+    uri: &Uri,
+    results: Vec<T>,
+    next_cursor: Option<&str>,
+) -> Result<Response> {
+    let link = next_cursor.map(|cursor| format!("<{}>; rel=\"next\"", with_cursor(uri, cursor)));
+    let body = serde_json::to_vec(&CursorPaginated {
+        results,
+        next_cursor: next_cursor.map(ToString::to_string),
+    })?;
+
+    let mut builder = Builder::new().status(StatusCode::OK).header(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+    );
+    if let Some(link) = link {
+        builder = builder.header(header::LINK, link);
+    }
+    Ok(builder.body(Body::from(body))?)
+}
+
+/// The path and query of `uri`, with the `cursor` query parameter set to
+/// `cursor`.
+fn with_cursor(uri: &Uri, cursor: &str) -> String {
+    let mut query = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty() && param.split('=').next() != Some("cursor"))
+        .collect::<Vec<_>>();
+    let cursor = format!("cursor={}", encode_query(cursor));
+    query.push(&cursor);
+    format!("{}?{}", uri.path(), query.join("&"))
+}
+
+/// Percent-encode a query string value, keeping only the unreserved
+/// characters.
+fn encode_query(value: &str) -> String {
+    use std::fmt::Write as _;
+
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            // writing to a `String` cannot fail
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// The `application/problem+json` content type of [`problem`] responses.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) problem details body.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProblemDetails {
+    /// A URI reference identifying the problem type
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// A short, human-readable summary of the problem type
+    pub title: String,
+    /// The HTTP status code
+    pub status: u16,
+    /// A human-readable explanation specific to this occurrence
+    pub detail: String,
+}
+
+/// Returns a [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) problem
+/// details response, with the `application/problem+json` content type.
+///
+/// # Example:
+///
+/// ```rust
+/// use axum::http::StatusCode;
+/// use loco_rs::prelude::*;
+///
+/// async fn endpoint() -> Result<Response> {
+///    format::problem(
+///        StatusCode::FORBIDDEN,
+///        "https://example.com/probs/out-of-credit",
+///        "You do not have enough credit.",
+///        "Your current balance is 30, but that costs 50.",
+///    )
+/// }
+/// ```
+///
+/// # Errors
+///
+/// This function will return an error if serde fails
+pub fn problem(
+    status: StatusCode,
+    problem_type: &str,
+    title: &str,
+    detail: &str,
+) -> Result<Response> {
+    render()
+        .status(status)
+        .json(ProblemDetails {
+            problem_type: problem_type.to_string(),
+            title: title.to_string(),
+            status: status.as_u16(),
+            detail: detail.to_string(),
+        })
+        .with_header(header::CONTENT_TYPE, PROBLEM_JSON)
+}
+
 /// Respond with empty json (`{}`)
 ///
 /// # Errors
@@ -155,6 +367,70 @@ pub fn redirect(to: &str) -> Result<Response> {
     Ok(Redirect::to(to).into_response())
 }
 
+/// Streams a file from disk, without reading it into memory, for large
+/// downloads. The content type is guessed from the file extension and the
+/// `Content-Length` is set from the file size.
+///
+/// # Example:
+///
+/// ```rust
+/// use loco_rs::prelude::*;
+///
+/// async fn download() -> Result<Response> {
+///    format::file_stream("exports/report.csv").await
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::NotFound`] when the file does not exist, or an error when
+/// it could not be read.
+pub async fn file_stream(path: impl AsRef<Path>) -> Result<Response> {
+    let path = path.as_ref();
+    let file = tokio::fs::File::open(path).await.map_err(|err| {
+        if err.kind() == std::io::ErrorKind::NotFound {
+            Error::NotFound
+        } else {
+            Error::IO(err)
+        }
+    })?;
+    let len = file.metadata().await?.len();
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, content_type(path))
+        .header(header::CONTENT_LENGTH, len)
+        .body(Body::from_stream(ReaderStream::new(file)))?)
+}
+
+/// The content type of a file, guessed from its extension.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("gz") => "application/gzip",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("mp4") => "video/mp4",
+        Some("wasm") => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
 /// Render template located by `key`
 ///
 /// # Errors
@@ -338,3 +614,198 @@ impl Default for RenderBuilder {
 pub fn render() -> RenderBuilder {
     RenderBuilder::new()
 }
+
+/// Adds headers to an already formatted response.
+///
+/// # Example:
+///
+/// ```rust
+/// use loco_rs::prelude::*;
+///
+/// async fn endpoint() -> Result<Response> {
+///    format::json(serde_json::json!({ "ok": true }))
+///        .with_header("x-total-count", "42")
+///        .with_header("cache-control", "no-store")
+/// }
+/// ```
+pub trait ResponseExt: Sized {
+    /// Add a single header, replacing any header with the same name.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the response is an error, or
+    /// the header name or value is illegal
+    fn with_header<K, V>(self, key: K, value: V) -> Result<Response>
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<axum::http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<axum::http::Error>;
+}
+
+impl ResponseExt for Result<Response> {
+    fn with_header<K, V>(self, key: K, value: V) -> Result<Response>
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<axum::http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<axum::http::Error>,
+    {
+        let mut response = self?;
+        let key = HeaderName::try_from(key).map_err(Into::into)?;
+        let value = HeaderValue::try_from(value).map_err(Into::into)?;
+        response.headers_mut().insert(key, value);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn can_stream_a_file() {
+        let path =
+            std::env::temp_dir().join(format!("loco-file-stream-{}.csv", std::process::id()));
+        std::fs::write(&path, "id,name\n1,loco\n").unwrap();
+
+        let response = file_stream(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "15");
+        assert_eq!(
+            response_body(response).await,
+            "id,name\n1,loco\n".as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn streaming_a_missing_file_is_not_found() {
+        let res = file_stream("does/not/exist.csv").await;
+
+        assert!(matches!(res, Err(Error::NotFound)));
+    }
+
+    async fn response_body(response: Response) -> Bytes {
+        to_bytes(response.into_body(), usize::MAX).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn cursor_paginated_links_the_next_page() {
+        let uri = Uri::from_static("/api/notes");
+        let response = cursor_paginated(&uri, vec![1, 2], Some("abc+/=")).unwrap();
+
+        assert_eq!(
+            response.headers()[header::LINK],
+            "</api/notes?cursor=abc%2B%2F%3D>; rel=\"next\""
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(&response_body(response).await).unwrap();
+        assert_eq!(body, json!({ "results": [1, 2], "next_cursor": "abc+/=" }));
+    }
+
+    #[test]
+    fn cursor_link_keeps_the_other_query_params() {
+        let uri = Uri::from_static("/api/notes?status=open&cursor=old&sort=-created_at");
+
+        assert_eq!(
+            with_cursor(&uri, "new"),
+            "/api/notes?status=open&sort=-created_at&cursor=new"
+        );
+    }
+
+    #[tokio::test]
+    async fn cursor_paginated_last_page_has_no_next_cursor() {
+        let response = cursor_paginated(&Uri::from_static("/api/notes"), vec![3], None).unwrap();
+
+        assert!(response.headers().get(header::LINK).is_none());
+        let body: serde_json::Value =
+            serde_json::from_slice(&response_body(response).await).unwrap();
+        assert_eq!(body, json!({ "results": [3] }));
+    }
+
+    #[tokio::test]
+    async fn json_raw_passes_body_through() {
+        let raw = r#"{ "b": 2,  "a": [1, 2] }"#;
+        let response = json_raw(raw).unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            mime::APPLICATION_JSON.as_ref()
+        );
+        assert_eq!(response_body(response).await, raw.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn json_value_sets_content_type() {
+        let response = json_value(json!({ "ok": true })).unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            mime::APPLICATION_JSON.as_ref()
+        );
+        assert_eq!(response_body(response).await, r#"{"ok":true}"#.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn can_add_headers_to_a_response() {
+        let response = json(json!({ "ok": true }))
+            .with_header("x-total-count", "42")
+            .with_header(header::CACHE_CONTROL, "no-store")
+            .with_header("x-total-count", "43")
+            .unwrap();
+
+        assert_eq!(response.headers().get("x-total-count").unwrap(), "43");
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            mime::APPLICATION_JSON.as_ref()
+        );
+        assert_eq!(response_body(response).await, r#"{"ok":true}"#.as_bytes());
+    }
+
+    #[test]
+    fn with_header_rejects_illegal_headers() {
+        assert!(empty().with_header("bad header", "value").is_err());
+        assert!(empty().with_header("x-ok", "bad\nvalue").is_err());
+    }
+
+    #[tokio::test]
+    async fn can_respond_with_problem_details() {
+        let response = problem(
+            StatusCode::FORBIDDEN,
+            "https://example.com/probs/out-of-credit",
+            "You do not have enough credit.",
+            "Your current balance is 30, but that costs 50.",
+        )
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(&response_body(response).await).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "You do not have enough credit.",
+                "status": 403,
+                "detail": "Your current balance is 30, but that costs 50.",
+            })
+        );
+    }
+}
@@ -1,39 +1,2137 @@
 //! This module contains a base routes related to health checks and status
 //! reporting. These routes are commonly used to monitor the health of the
 //! application and its dependencies.
+//!
+//! Every dependency is a [`HealthCheck`] component. Its [`Criticality`]
+//! decides how a failure affects the overall status:
+//!
+//! * [`Criticality::Critical`] - a failing component makes the application
+//!   unhealthy.
+//! * [`Criticality::NonCritical`] - a failing component degrades the
+//!   application.
+//! * [`Criticality::Informational`] - the component is reported, but never
+//!   affects the overall status.
+//!
+//! Components are registered in the [`HealthRegistry`] of the
+//! [`AppContext`].
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
 
-use axum::{extract::State, response::Response, routing::get};
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use futures_util::future::join_all;
 use serde::Serialize;
+use tokio::sync::Semaphore;
+use tower::Service;
 
 use super::{format, routes::Routes};
-use crate::{app::AppContext, redis, Result};
+use crate::{app::AppContext, config, redis, Error, Result};
+
+/// How a failing component affects the overall health status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Criticality {
+    /// A failure makes the application unhealthy
+    #[default]
+    Critical,
+    /// A failure degrades the application
+    NonCritical,
+    /// Never affects the overall status
+    Informational,
+}
+
+/// The outcome of a single health check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    Ok,
+    Degraded(String),
+    Error(String),
+}
+
+/// The status of a component, serialized as `"ok"`, `"degraded"`,
+/// `"timeout"` or `"error"`. This representation is part of the health
+/// response contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentStatus {
+    Ok,
+    Degraded,
+    /// The check did not complete before the registry deadline. Rolls up
+    /// like an error.
+    Timeout,
+    Error,
+}
+
+/// The rolled up status of the application, with the same representation as
+/// [`ComponentStatus`].
+pub type Status = ComponentStatus;
+
+/// A health check component.
+#[async_trait]
+pub trait HealthCheck<T: Send + Sync + Clone>: Send + Sync {
+    /// The component name, used as its key in the health response.
+    fn name(&self) -> String;
+
+    /// How a failure of this component affects the overall status.
+    fn criticality(&self) -> Criticality {
+        Criticality::Critical
+    }
+
+    /// Check the component.
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus;
+
+    /// Check the component, also reporting measured values (e.g. a lag) in
+    /// the component `details`. Defaults to [`HealthCheck::check`] without
+    /// details.
+    async fn check_with_details(
+        &self,
+        ctx: &AppContext<T>,
+    ) -> (HealthStatus, BTreeMap<String, serde_json::Value>) {
+        (self.check(ctx).await, BTreeMap::new())
+    }
+}
+
+/// The health of a single component:
+///
+/// ```json
+/// {
+///   "status": "degraded",
+///   "criticality": "non_critical",
+///   "message": "slow responses",
+///   "latency_ms": 12
+/// }
+/// ```
+///
+/// `message` is only set when the component is not ok, `latency_ms` is how
+/// long the check took, and `details` holds the measured values reported by
+/// [`HealthCheck::check_with_details`].
+#[derive(Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: ComponentStatus,
+    pub criticality: Criticality,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, serde_json::Value>,
+}
+
+/// The last runtime configuration reload, see
+/// [`crate::boot::reload_config`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigReload {
+    /// How many times the configuration was reloaded
+    pub generation: u64,
+    /// The log level in effect after the reload
+    pub log_level: String,
+}
 
 /// Represents the health status of the application.
-#[derive(Serialize)]
-struct Health {
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
     pub ok: bool,
+    pub status: Status,
+    pub components: BTreeMap<String, ComponentHealth>,
+    /// Absent until the configuration is reloaded at runtime
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<ConfigReload>,
 }
 
-/// Check the healthiness of the application bt ping to the redis and the DB to
-/// insure that connection
-async fn health<T: Send + Sync + Clone>(State(ctx): State<AppContext<T>>) -> Result<Response> {
-    let mut is_ok = match ctx.db.ping().await {
-        Ok(()) => true,
-        Err(error) => {
-            tracing::error!(err.msg = %error, err.detail = ?error, "health_db_ping_error");
-            false
+impl HealthReport {
+    /// Rename the components found in `aliases` (registered name to
+    /// reported name), e.g. to match the names a monitoring dashboard
+    /// expects. The other components keep their names.
+    ///
+    /// # Errors
+    ///
+    /// When two components would be reported under the same name
+    pub fn with_aliases(mut self, aliases: &BTreeMap<String, String>) -> Result<Self> {
+        let mut components = BTreeMap::new();
+        for (name, component) in self.components {
+            let reported = aliases.get(&name).cloned().unwrap_or_else(|| name.clone());
+            if components.insert(reported.clone(), component).is_some() {
+                return Err(Error::Message(format!(
+                    "health component `{name}` is reported as `{reported}`, which is taken by \
+                     another component"
+                )));
+            }
         }
-    };
-    if let Some(pool) = ctx.queue {
-        if let Err(error) = redis::ping(&pool).await {
-            tracing::error!(err.msg = %error, err.detail = ?error, "health_redis_ping_error");
-            is_ok = false;
+        self.components = components;
+        Ok(self)
+    }
+}
+
+/// The registered health check components, shared through
+/// [`AppContext::health`].
+///
+/// Checks can be registered and unregistered at runtime, e.g. when a
+/// feature-flagged dependency comes and goes. The health and readiness
+/// endpoints read the registry on each request.
+///
+/// The checks run concurrently. With many checks hitting a shared resource,
+/// bound how many run at once with the `server.health.concurrency_limit`
+/// setting or [`HealthRegistry::set_concurrency_limit`]. To answer within a
+/// bounded time, set an overall deadline with
+/// [`HealthRegistry::set_deadline`]: the checks still pending when it elapses
+/// are reported as timed out, alongside the results of the completed ones.
+pub struct HealthRegistry<T: Send + Sync + Clone> {
+    checks: Arc<RwLock<Vec<Arc<dyn HealthCheck<T>>>>>,
+    limit: Arc<RwLock<Option<Arc<Semaphore>>>>,
+    deadline: Arc<RwLock<Option<Duration>>>,
+    rate_limit: Arc<RwLock<Option<HealthRateLimit>>>,
+    aliases: Arc<RwLock<BTreeMap<String, String>>>,
+    config: Arc<RwLock<Option<ConfigReload>>>,
+}
+
+impl<T: Send + Sync + Clone> Clone for HealthRegistry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            checks: self.checks.clone(),
+            limit: self.limit.clone(),
+            deadline: self.deadline.clone(),
+            rate_limit: self.rate_limit.clone(),
+            aliases: self.aliases.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<T: Send + Sync + Clone> Default for HealthRegistry<T> {
+    fn default() -> Self {
+        Self {
+            checks: Arc::default(),
+            limit: Arc::default(),
+            deadline: Arc::default(),
+            rate_limit: Arc::default(),
+            aliases: Arc::default(),
+            config: Arc::default(),
+        }
+    }
+}
+
+impl<T: Send + Sync + Clone + 'static> HealthRegistry<T> {
+    /// The built-in checks: the database, and the redis queue when
+    /// configured.
+    #[must_use]
+    pub fn with_defaults() -> Self {
+        let registry = Self::default();
+        #[cfg(feature = "with-db")]
+        let registry = registry.with_check(DbCheck);
+        registry.with_check(RedisCheck)
+    }
+
+    /// The built-in checks (see [`HealthRegistry::with_defaults`]), set up
+    /// with the `server.health` configuration.
+    ///
+    /// # Errors
+    ///
+    /// When the configured aliases are invalid, see
+    /// [`HealthRegistry::set_aliases`]
+    pub fn from_config(config: &config::Health) -> Result<Self> {
+        let registry = Self::with_defaults();
+        registry.set_concurrency_limit(config.concurrency_limit);
+        registry.set_rate_limit(config.rate_limit.as_ref().map(HealthRateLimit::from_config));
+        registry.set_aliases(config.aliases.clone())?;
+        Ok(registry)
+    }
+
+    /// Add a health check component, see [`HealthRegistry::register_check`].
+    #[must_use]
+    pub fn with_check(self, check: impl HealthCheck<T> + 'static) -> Self {
+        self.register_check(check);
+        self
+    }
+
+    /// Bound how many checks run concurrently, see
+    /// [`HealthRegistry::set_concurrency_limit`].
+    #[must_use]
+    pub fn with_concurrency_limit(self, limit: NonZeroUsize) -> Self {
+        self.set_concurrency_limit(Some(limit));
+        self
+    }
+
+    /// Bound how many checks run concurrently, across all the health and
+    /// readiness requests. `None` runs all the checks at once.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    pub fn set_concurrency_limit(&self, limit: Option<NonZeroUsize>) {
+        *self.limit.write().unwrap() = limit.map(|permits| Arc::new(Semaphore::new(permits.get())));
+    }
+
+    /// Bound how long a run takes, see [`HealthRegistry::set_deadline`].
+    #[must_use]
+    pub fn with_deadline(self, deadline: Duration) -> Self {
+        self.set_deadline(Some(deadline));
+        self
+    }
+
+    /// Bound how long a run takes overall, including waiting on the
+    /// concurrency limit. When the deadline elapses the completed checks are
+    /// reported as is, and the pending ones with a `timeout` status. `None`
+    /// waits for all the checks.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    pub fn set_deadline(&self, deadline: Option<Duration>) {
+        *self.deadline.write().unwrap() = deadline;
+    }
+
+    /// Rate limit the health route, see [`HealthRateLimit`]. `None` removes
+    /// the limit.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    pub fn set_rate_limit(&self, limit: Option<HealthRateLimit>) {
+        *self.rate_limit.write().unwrap() = limit;
+    }
+
+    fn rate_limit(&self) -> Option<HealthRateLimit> {
+        self.rate_limit.read().unwrap().clone()
+    }
+
+    /// Check a request of the health or readiness endpoints against the rate
+    /// limit, returning the `429` response when it is over the limit.
+    pub(crate) fn limit(&self, request: &Request) -> Option<Response> {
+        self.rate_limit().and_then(|limit| limit.check(request))
+    }
+
+    /// Report the components of the health route under the names mapped in
+    /// `aliases`, see [`HealthReport::with_aliases`].
+    ///
+    /// # Errors
+    ///
+    /// When two components are mapped to the same alias
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    pub fn set_aliases(&self, aliases: BTreeMap<String, String>) -> Result<()> {
+        let mut reported = BTreeMap::new();
+        for (name, alias) in &aliases {
+            if let Some(other) = reported.insert(alias, name) {
+                return Err(Error::Message(format!(
+                    "health components `{other}` and `{name}` are both aliased to `{alias}`"
+                )));
+            }
+        }
+        *self.aliases.write().unwrap() = aliases;
+        Ok(())
+    }
+
+    fn aliases(&self) -> BTreeMap<String, String> {
+        self.aliases.read().unwrap().clone()
+    }
+
+    /// Record a runtime configuration reload, reported in the health
+    /// response with the reload generation and the log level in effect.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    pub fn record_config_reload(&self, log_level: &str) {
+        let mut config = self.config.write().unwrap();
+        let generation = config.as_ref().map_or(0, |reload| reload.generation) + 1;
+        *config = Some(ConfigReload {
+            generation,
+            log_level: log_level.to_string(),
+        });
+    }
+
+    /// Register a health check component, replacing a registered component
+    /// with the same name.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    pub fn register_check(&self, check: impl HealthCheck<T> + 'static) {
+        let name = check.name();
+        let mut checks = self.checks.write().unwrap();
+        checks.retain(|registered| registered.name() != name);
+        checks.push(Arc::new(check));
+    }
+
+    /// Unregister a health check component by name. Returns whether it was
+    /// registered.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    #[must_use]
+    pub fn unregister_check(&self, name: &str) -> bool {
+        let mut checks = self.checks.write().unwrap();
+        let len = checks.len();
+        checks.retain(|registered| registered.name() != name);
+        checks.len() != len
+    }
+
+    /// The registered components names.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        self.checks
+            .read()
+            .unwrap()
+            .iter()
+            .map(|check| check.name())
+            .collect()
+    }
+
+    /// Run all the registered checks and roll up their statuses.
+    ///
+    /// # Panics
+    ///
+    /// When the registry lock is poisoned
+    pub async fn run(&self, ctx: &AppContext<T>) -> HealthReport {
+        let checks = self.checks.read().unwrap().clone();
+        let limit = self.limit.read().unwrap().clone();
+        let deadline = *self.deadline.read().unwrap();
+        let run_started = Instant::now();
+
+        let results = join_all(checks.iter().map(|check| {
+            let limit = limit.clone();
+            let run = async move {
+                let _permit = match &limit {
+                    Some(semaphore) => semaphore.acquire().await.ok(),
+                    None => None,
+                };
+                let started = Instant::now();
+                let result = check.check_with_details(ctx).await;
+                (result, started.elapsed())
+            };
+            async move {
+                let result = match deadline {
+                    Some(deadline) => tokio::time::timeout_at((run_started + deadline).into(), run)
+                        .await
+                        .ok(),
+                    None => Some(run.await),
+                };
+                (check, result)
+            }
+        }))
+        .await;
+
+        let mut components = BTreeMap::new();
+        for (check, result) in results {
+            let component = if let Some(((result, details), latency)) = result {
+                let (status, message) = match result {
+                    HealthStatus::Ok => (ComponentStatus::Ok, None),
+                    HealthStatus::Degraded(message) => (ComponentStatus::Degraded, Some(message)),
+                    HealthStatus::Error(message) => (ComponentStatus::Error, Some(message)),
+                };
+                ComponentHealth {
+                    status,
+                    criticality: check.criticality(),
+                    message,
+                    latency_ms: Some(as_millis(latency)),
+                    details,
+                }
+            } else {
+                let elapsed = as_millis(run_started.elapsed());
+                ComponentHealth {
+                    status: ComponentStatus::Timeout,
+                    criticality: check.criticality(),
+                    message: Some(format!("timed out after {elapsed}ms")),
+                    latency_ms: Some(elapsed),
+                    details: BTreeMap::new(),
+                }
+            };
+            components.insert(check.name(), component);
+        }
+
+        let status = rollup(components.values());
+        HealthReport {
+            ok: status != Status::Error,
+            status,
+            components,
+            config: self.config.read().unwrap().clone(),
+        }
+    }
+}
+
+fn as_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Compute the overall status from the components statuses and criticality.
+fn rollup<'a>(components: impl Iterator<Item = &'a ComponentHealth>) -> Status {
+    components
+        .map(
+            |component| match (component.criticality, component.status) {
+                (Criticality::Informational, _) => Status::Ok,
+                (Criticality::NonCritical, Status::Error | Status::Timeout) => Status::Degraded,
+                (_, Status::Timeout) => Status::Error,
+                (_, status) => status,
+            },
+        )
+        .max()
+        .unwrap_or(Status::Ok)
+}
+
+/// Check the healthiness of the application by running the registered
+/// components checks, unless the request is over the rate limit.
+async fn health<T: Send + Sync + Clone + 'static>(
+    State(ctx): State<AppContext<T>>,
+    request: Request,
+) -> Result<Response> {
+    if let Some(limited) = ctx.health.limit(&request) {
+        return Ok(limited);
+    }
+    let aliases = ctx.health.aliases();
+    let report = ctx
+        .health
+        .run(&ctx)
+        .await
+        .with_aliases(&aliases)
+        .map_err(|err| {
+            tracing::error!(err.msg = %err, "health_aliases_error");
+            Error::InternalServerError
+        })?;
+    format::json(report)
+}
+
+/// Pings the database.
+#[cfg(feature = "with-db")]
+pub struct DbCheck;
+
+#[cfg(feature = "with-db")]
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for DbCheck {
+    fn name(&self) -> String {
+        "db".to_string()
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        match ctx.db.ping().await {
+            Ok(()) => HealthStatus::Ok,
+            Err(error) => {
+                tracing::error!(err.msg = %error, err.detail = ?error, "health_db_ping_error");
+                HealthStatus::Error(error.to_string())
+            }
+        }
+    }
+}
+
+/// Checks the replication lag of a read replica, reported as
+/// `replication_lag_seconds` in the component details.
+///
+/// The component fails when the lag exceeds the threshold, so the application
+/// is not ready while replica-backed reads may miss recent writes.
+///
+/// Opt-in, registered with the replica connection:
+///
+/// ```rust,ignore
+/// let check = ReplicationLagCheck::new(Duration::from_secs(5)).with_connection(replica);
+/// ctx.health.register_check(check);
+/// ```
+///
+/// The lag is queried with `pg_last_xact_replay_timestamp()` on Postgres.
+/// Other databases need a query returning the lag in a `lag_seconds` column,
+/// set with [`ReplicationLagCheck::with_query`].
+#[cfg(feature = "with-db")]
+pub struct ReplicationLagCheck {
+    threshold: Duration,
+    query: Option<String>,
+    db: Option<sea_orm::DatabaseConnection>,
+}
+
+/// The Postgres replica lag, `0` on a primary.
+#[cfg(feature = "with-db")]
+const POSTGRES_LAG_QUERY: &str = "SELECT COALESCE(EXTRACT(EPOCH FROM (now() - \
+                                  pg_last_xact_replay_timestamp())), 0)::float8 AS lag_seconds";
+
+#[cfg(feature = "with-db")]
+impl ReplicationLagCheck {
+    /// Check the replication lag of the application database against
+    /// `threshold`.
+    #[must_use]
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            query: None,
+            db: None,
+        }
+    }
+
+    /// Query the lag with `query`, returning a single row with the lag in
+    /// seconds in a `lag_seconds` column.
+    #[must_use]
+    pub fn with_query(mut self, query: &str) -> Self {
+        self.query = Some(query.to_string());
+        self
+    }
+
+    /// Check the replica behind `db` instead of the application database.
+    #[must_use]
+    pub fn with_connection(mut self, db: sea_orm::DatabaseConnection) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    async fn lag_seconds(&self, db: &sea_orm::DatabaseConnection) -> Result<f64> {
+        use sea_orm::{ConnectionTrait, DatabaseBackend, Statement};
+
+        let backend = db.get_database_backend();
+        let query = match (&self.query, backend) {
+            (Some(query), _) => query.as_str(),
+            (None, DatabaseBackend::Postgres) => POSTGRES_LAG_QUERY,
+            (None, backend) => {
+                return Err(Error::Message(format!(
+                    "no replication lag query for {backend:?}, set one with `with_query`"
+                )))
+            }
+        };
+        let row = db
+            .query_one(Statement::from_string(backend, query))
+            .await?
+            .ok_or_else(|| {
+                Error::Message("the replication lag query returned no row".to_string())
+            })?;
+        Ok(row.try_get::<f64>("", "lag_seconds")?)
+    }
+}
+
+#[cfg(feature = "with-db")]
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for ReplicationLagCheck {
+    fn name(&self) -> String {
+        "replication_lag".to_string()
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        self.check_with_details(ctx).await.0
+    }
+
+    async fn check_with_details(
+        &self,
+        ctx: &AppContext<T>,
+    ) -> (HealthStatus, BTreeMap<String, serde_json::Value>) {
+        let lag = match self.lag_seconds(self.db.as_ref().unwrap_or(&ctx.db)).await {
+            Ok(lag) => lag,
+            Err(error) => {
+                tracing::error!(err.msg = %error, err.detail = ?error, "health_replication_lag_error");
+                return (HealthStatus::Error(error.to_string()), BTreeMap::new());
+            }
+        };
+        let details = BTreeMap::from([("replication_lag_seconds".to_string(), lag.into())]);
+        let threshold = self.threshold.as_secs_f64();
+        if lag > threshold {
+            let message = format!("replication lag of {lag}s exceeds {threshold}s");
+            (HealthStatus::Error(message), details)
+        } else {
+            (HealthStatus::Ok, details)
+        }
+    }
+}
+
+/// Pings the redis queue, when configured.
+pub struct RedisCheck;
+
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for RedisCheck {
+    fn name(&self) -> String {
+        "redis".to_string()
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        let Some(pool) = &ctx.queue else {
+            return HealthStatus::Ok;
+        };
+        match redis::ping(pool).await {
+            Ok(()) => HealthStatus::Ok,
+            Err(error) => {
+                tracing::error!(err.msg = %error, err.detail = ?error, "health_redis_ping_error");
+                HealthStatus::Error(error.to_string())
+            }
+        }
+    }
+}
+
+/// Checks that required secrets are present in the environment and not blank,
+/// catching botched secret rotations before they cause authentication failures.
+///
+/// The missing variables are listed in the component `missing` detail.
+///
+/// Opt-in:
+///
+/// ```rust
+/// use loco_rs::{app::AppContext, controller::health::SecretsCheck};
+///
+/// fn register(ctx: &AppContext<()>) {
+///     ctx.health
+///         .register_check(SecretsCheck::new(&["STRIPE_API_KEY", "JWT_SECRET"]));
+/// }
+/// ```
+pub struct SecretsCheck {
+    vars: Vec<String>,
+}
+
+impl SecretsCheck {
+    /// Check the given environment variables.
+    #[must_use]
+    pub fn new(vars: &[&str]) -> Self {
+        Self {
+            vars: vars.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn missing(&self) -> Vec<String> {
+        self.vars
+            .iter()
+            .filter(|var| std::env::var(var).map_or(true, |value| value.trim().is_empty()))
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for SecretsCheck {
+    fn name(&self) -> String {
+        "secrets".to_string()
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        self.check_with_details(ctx).await.0
+    }
+
+    async fn check_with_details(
+        &self,
+        _ctx: &AppContext<T>,
+    ) -> (HealthStatus, BTreeMap<String, serde_json::Value>) {
+        let missing = self.missing();
+        if missing.is_empty() {
+            return (HealthStatus::Ok, BTreeMap::new());
+        }
+        let message = format!("missing: {}", missing.join(", "));
+        let details = BTreeMap::from([("missing".to_string(), missing.into())]);
+        (HealthStatus::Error(message), details)
+    }
+}
+
+/// Confirms the JWT signing key is loaded and usable by signing and verifying
+/// a short-lived test token, catching key misconfiguration before users hit
+/// login failures.
+///
+/// The key is read from the `auth.jwt.secret` configuration, or given with
+/// [`AuthKeyCheck::with_secret`] when it comes from elsewhere (e.g. a file
+/// or a KMS). Opt-in:
+///
+/// ```rust
+/// use loco_rs::{app::AppContext, controller::health::AuthKeyCheck};
+///
+/// fn register(ctx: &AppContext<()>) {
+///     ctx.health.register_check(AuthKeyCheck::default());
+/// }
+/// ```
+#[cfg(feature = "auth_jwt")]
+#[derive(Default)]
+pub struct AuthKeyCheck {
+    secret: Option<String>,
+}
+
+#[cfg(feature = "auth_jwt")]
+impl AuthKeyCheck {
+    /// Check the given signing key instead of the configured one.
+    #[must_use]
+    pub fn with_secret(secret: &str) -> Self {
+        Self {
+            secret: Some(secret.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "auth_jwt")]
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for AuthKeyCheck {
+    fn name(&self) -> String {
+        "auth_key".to_string()
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        let secret = match &self.secret {
+            Some(secret) => secret.as_str(),
+            None => match ctx.config.get_jwt_config() {
+                Ok(jwt) => jwt.secret.as_str(),
+                Err(_) => return HealthStatus::Error("no JWT config found".to_string()),
+            },
+        };
+        if secret.trim().is_empty() {
+            return HealthStatus::Error("the JWT secret is empty".to_string());
+        }
+
+        let jwt = crate::auth::jwt::JWT::new(secret);
+        let token = match jwt.generate_token(&60, "health".to_string(), None) {
+            Ok(token) => token,
+            Err(err) => return HealthStatus::Error(format!("cannot sign a token: {err}")),
+        };
+        match jwt.validate(&token) {
+            Ok(data) if data.claims.pid == "health" => HealthStatus::Ok,
+            Ok(_) => HealthStatus::Error("verified a token with different claims".to_string()),
+            Err(err) => HealthStatus::Error(format!("cannot verify a token: {err}")),
+        }
+    }
+}
+
+/// Verifies the application can actually write to and read from its storage:
+/// puts a tiny unique object, reads it back, compares and deletes it.
+///
+/// This catches permission and consistency issues a connectivity check misses.
+///
+/// Opt-in, the objects are written under the `_health` prefix unless set
+/// with [`StorageRwCheck::with_prefix`]:
+///
+/// ```rust
+/// use loco_rs::{app::AppContext, controller::health::StorageRwCheck};
+///
+/// fn register(ctx: &AppContext<()>) {
+///     ctx.health
+///         .register_check(StorageRwCheck::default().with_prefix("probes/health"));
+/// }
+/// ```
+pub struct StorageRwCheck {
+    prefix: String,
+}
+
+impl Default for StorageRwCheck {
+    fn default() -> Self {
+        Self {
+            prefix: "_health".to_string(),
+        }
+    }
+}
+
+impl StorageRwCheck {
+    /// Write the probe objects under the given key prefix.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for StorageRwCheck {
+    fn name(&self) -> String {
+        "storage_rw".to_string()
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        let id = uuid::Uuid::new_v4().to_string();
+        let path = std::path::PathBuf::from(format!("{}/{id}", self.prefix));
+        let content = bytes::Bytes::from(id.clone());
+
+        if let Err(err) = ctx.storage.upload(&path, &content).await {
+            return HealthStatus::Error(format!("write failed: {err}"));
+        }
+        let read = ctx.storage.download::<String>(&path).await;
+        let deleted = ctx.storage.delete(&path).await;
+        match (read, deleted) {
+            (Err(err), _) => HealthStatus::Error(format!("read failed: {err}")),
+            (Ok(read), _) if read != id => {
+                HealthStatus::Error("read back a different content".to_string())
+            }
+            (Ok(_), Err(err)) => HealthStatus::Error(format!("delete failed: {err}")),
+            (Ok(_), Ok(())) => HealthStatus::Ok,
+        }
+    }
+}
+
+/// A health check component backed by a closure, so any external dependency
+/// (e.g. a remote feature-flag service) can be checked without a dedicated
+/// type.
+///
+/// # Example
+///
+/// ```rust
+/// use loco_rs::{
+///     app::AppContext,
+///     controller::health::{Criticality, HealthStatus, PollableHealthCheck},
+/// };
+///
+/// fn register(ctx: &AppContext<()>) {
+///     ctx.health.register_check(
+///         PollableHealthCheck::new("flags", |_ctx: AppContext<()>| async {
+///             // ping the feature-flag service here
+///             HealthStatus::Ok
+///         })
+///         .with_criticality(Criticality::NonCritical),
+///     );
+/// }
+/// ```
+pub struct PollableHealthCheck<F> {
+    name: String,
+    criticality: Criticality,
+    poll: F,
+}
+
+impl<F> PollableHealthCheck<F> {
+    /// Create a critical component named `name`, checked by running `poll`
+    /// with a clone of the application context.
+    pub fn new(name: &str, poll: F) -> Self {
+        Self {
+            name: name.to_string(),
+            criticality: Criticality::Critical,
+            poll,
+        }
+    }
+
+    /// Set how a failure of this component affects the overall status.
+    #[must_use]
+    pub fn with_criticality(mut self, criticality: Criticality) -> Self {
+        self.criticality = criticality;
+        self
+    }
+}
+
+#[async_trait]
+impl<T, F, Fut> HealthCheck<T> for PollableHealthCheck<F>
+where
+    T: Send + Sync + Clone + 'static,
+    F: Fn(AppContext<T>) -> Fut + Send + Sync,
+    Fut: Future<Output = HealthStatus> + Send,
+{
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn criticality(&self) -> Criticality {
+        self.criticality
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        (self.poll)(ctx.clone()).await
+    }
+}
+
+/// Self-tests a critical route end-to-end, with an in-process request verifying
+/// it responds with a `2xx` status within a deadline.
+///
+/// This catches routing and middleware regressions that dependency pings miss.
+///
+/// Opt-in, registered once the application router is built:
+///
+/// ```rust,ignore
+/// async fn after_routes(router: AxumRouter, ctx: &AppContext) -> Result<AxumRouter> {
+///     let check = SelfCheck::new(router.clone(), "/api/critical", Duration::from_millis(500))?;
+///     ctx.health.register_check(check);
+///     Ok(router)
+/// }
+/// ```
+pub struct SelfCheck {
+    router: axum::Router,
+    path: String,
+    deadline: Duration,
+}
+
+impl SelfCheck {
+    /// Check that `path` responds successfully within `deadline`.
+    ///
+    /// # Errors
+    ///
+    /// When `path` is a health route, which would recurse into the health
+    /// checks.
+    pub fn new(router: axum::Router, path: &str, deadline: Duration) -> Result<Self> {
+        if SELF_CHECK_EXCLUDED.contains(&path) {
+            return Err(Error::Message(format!(
+                "the self check cannot target the `{path}` health route"
+            )));
+        }
+        Ok(Self {
+            router,
+            path: path.to_string(),
+            deadline,
+        })
+    }
+}
+
+/// The routes running the health checks.
+const SELF_CHECK_EXCLUDED: &[&str] = &["/_health", "/_readiness"];
+
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for SelfCheck {
+    fn name(&self) -> String {
+        "self_check".to_string()
+    }
+
+    async fn check(&self, _ctx: &AppContext<T>) -> HealthStatus {
+        let request = match Request::get(&self.path).body(Body::empty()) {
+            Ok(request) => request,
+            Err(error) => return HealthStatus::Error(error.to_string()),
+        };
+        let mut router = self.router.clone();
+        match tokio::time::timeout(self.deadline, router.call(request)).await {
+            Ok(Ok(response)) if response.status().is_success() => HealthStatus::Ok,
+            Ok(Ok(response)) => HealthStatus::Error(format!(
+                "{} responded with {}",
+                self.path,
+                response.status()
+            )),
+            Ok(Err(infallible)) => match infallible {},
+            Err(_) => HealthStatus::Error(format!(
+                "{} did not respond within {}ms",
+                self.path,
+                self.deadline.as_millis()
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MigrationState {
+    Running,
+    Completed,
+    Failed(String),
+}
+
+/// A shared signal tracking migrations run in the background after boot, so
+/// the application reports not ready until they complete.
+///
+/// Registered as a critical health check component named `migrations`:
+/// readiness fails while the migrations run and when they failed. See
+/// [`crate::db::migrate_in_background`].
+#[derive(Debug, Clone)]
+pub struct MigrationStatus(Arc<RwLock<MigrationState>>);
+
+impl Default for MigrationStatus {
+    fn default() -> Self {
+        Self(Arc::new(RwLock::new(MigrationState::Running)))
+    }
+}
+
+impl MigrationStatus {
+    /// A status of running migrations.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark the migrations as completed.
+    ///
+    /// # Panics
+    ///
+    /// When the status lock is poisoned
+    pub fn complete(&self) {
+        *self.0.write().unwrap() = MigrationState::Completed;
+    }
+
+    /// Mark the migrations as failed.
+    ///
+    /// # Panics
+    ///
+    /// When the status lock is poisoned
+    pub fn fail(&self, message: &str) {
+        *self.0.write().unwrap() = MigrationState::Failed(message.to_string());
+    }
+
+    /// Whether the migrations completed successfully.
+    ///
+    /// # Panics
+    ///
+    /// When the status lock is poisoned
+    #[must_use]
+    pub fn is_completed(&self) -> bool {
+        *self.0.read().unwrap() == MigrationState::Completed
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone> HealthCheck<T> for MigrationStatus {
+    fn name(&self) -> String {
+        "migrations".to_string()
+    }
+
+    async fn check(&self, _ctx: &AppContext<T>) -> HealthStatus {
+        let state = self.0.read().unwrap().clone();
+        match state {
+            MigrationState::Running => HealthStatus::Error("migrations are running".to_string()),
+            MigrationState::Completed => HealthStatus::Ok,
+            MigrationState::Failed(message) => HealthStatus::Error(message),
+        }
+    }
+}
+
+/// The cached outcome of a [`CachedCheck`].
+type CachedOutcome = (Instant, HealthStatus, BTreeMap<String, serde_json::Value>);
+
+/// Caches the outcome of a health check component for a TTL, so an expensive
+/// check (e.g. an external HTTP dependency) is not re-run on every probe while
+/// cheap ones stay fresh.
+///
+/// Each component has its own TTL.
+///
+/// ```rust,ignore
+/// ctx.health.register_check(CachedCheck::new(PaymentsCheck, Duration::from_secs(30)));
+/// ```
+pub struct CachedCheck<C> {
+    check: C,
+    ttl: Duration,
+    cached: Mutex<Option<CachedOutcome>>,
+}
+
+impl<C> CachedCheck<C> {
+    /// Serve the outcome of `check` from cache for `ttl` after each run.
+    pub fn new(check: C, ttl: Duration) -> Self {
+        Self {
+            check,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl<T, C> HealthCheck<T> for CachedCheck<C>
+where
+    T: Send + Sync + Clone,
+    C: HealthCheck<T>,
+{
+    fn name(&self) -> String {
+        self.check.name()
+    }
+
+    fn criticality(&self) -> Criticality {
+        self.check.criticality()
+    }
+
+    async fn check(&self, ctx: &AppContext<T>) -> HealthStatus {
+        self.check_with_details(ctx).await.0
+    }
+
+    async fn check_with_details(
+        &self,
+        ctx: &AppContext<T>,
+    ) -> (HealthStatus, BTreeMap<String, serde_json::Value>) {
+        let cached = self
+            .cached
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some((at, status, details)) = cached {
+            if at.elapsed() < self.ttl {
+                return (status, details);
+            }
+        }
+
+        let (status, details) = self.check.check_with_details(ctx).await;
+        *self.cached.lock().unwrap_or_else(PoisonError::into_inner) =
+            Some((Instant::now(), status.clone(), details.clone()));
+        (status, details)
+    }
+}
+
+/// A lightweight rate limit for the health route, protecting a public health
+/// endpoint from abuse without a full middleware stack.
+///
+/// Requests beyond the limit get a `429 Too Many Requests`, except the ones
+/// from the allowed sources (e.g. the orchestrator probing the application).
+///
+/// The limit applies to each source separately, over a fixed window, and to
+/// both the health and readiness endpoints. The source is the connection peer
+/// address, see [`axum::extract::ConnectInfo`]. When the peer is a trusted
+/// proxy, the source is the client address it set in `X-Forwarded-For`
+/// instead. When the peer is unknown, e.g. served on a unix socket behind a
+/// proxy, the source is taken from `X-Forwarded-For`.
+///
+/// Usually configured in `server.health.rate_limit`, see
+/// [`config::HealthRateLimit`], or at runtime:
+///
+/// ```rust,ignore
+/// let limit = HealthRateLimit::new(10, Duration::from_secs(1)).allow("10.0.0.1".parse().unwrap());
+/// ctx.health.set_rate_limit(Some(limit));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HealthRateLimit {
+    max_requests: u32,
+    per: Duration,
+    allowed: Vec<IpAddr>,
+    trusted_proxies: Vec<IpAddr>,
+    windows: Arc<Mutex<HashMap<Option<IpAddr>, Window>>>,
+}
+
+/// The start of a source window, and how many requests it counted.
+type Window = (Instant, u32);
+
+impl HealthRateLimit {
+    /// Allow at most `max_requests` requests from each source in every `per`
+    /// window.
+    #[must_use]
+    pub fn new(max_requests: u32, per: Duration) -> Self {
+        Self {
+            max_requests,
+            per,
+            allowed: vec![],
+            trusted_proxies: vec![],
+            windows: Arc::default(),
+        }
+    }
+
+    /// Build the rate limit from the `server.health.rate_limit`
+    /// configuration.
+    #[must_use]
+    pub fn from_config(config: &config::HealthRateLimit) -> Self {
+        let limit = Self::new(config.max_requests, Duration::from_millis(config.window));
+        let limit = config
+            .allow
+            .iter()
+            .fold(limit, |limit, source| limit.allow(*source));
+        config
+            .trusted_proxies
+            .iter()
+            .fold(limit, |limit, proxy| limit.trust_proxy(*proxy))
+    }
+
+    /// Never limit the requests from `source`, which are not counted either.
+    #[must_use]
+    pub fn allow(mut self, source: IpAddr) -> Self {
+        self.allowed.push(source);
+        self
+    }
+
+    /// Take the source of the requests from `proxy` in their
+    /// `X-Forwarded-For` header.
+    #[must_use]
+    pub fn trust_proxy(mut self, proxy: IpAddr) -> Self {
+        self.trusted_proxies.push(proxy);
+        self
+    }
+
+    /// The source address of a request: the connection peer, or the client
+    /// address set by a trusted proxy, or by the proxy in front when the
+    /// router is not served with the peer address. `None` when none is
+    /// known.
+    fn source(&self, request: &Request) -> Option<IpAddr> {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| peer.ip());
+        if let Some(peer) = peer.filter(|peer| !self.trusted_proxies.contains(peer)) {
+            return Some(peer);
         }
+        // the closest address not set by a trusted proxy, the ones before it
+        // may be forged by the client
+        request
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .rev()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(',').rev())
+            .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+            .find(|address| !self.trusted_proxies.contains(address))
+            .or(peer)
+    }
+
+    /// Count a request from `source`, returning how long to wait before
+    /// retrying when it is over the limit.
+    fn acquire(&self, source: Option<IpAddr>) -> Option<Duration> {
+        let mut windows = self.windows.lock().unwrap_or_else(PoisonError::into_inner);
+        windows.retain(|_, (start, _)| start.elapsed() < self.per);
+        let window = windows.entry(source).or_insert_with(|| (Instant::now(), 0));
+        let retry_after = if window.1 >= self.max_requests {
+            Some(self.per.saturating_sub(window.0.elapsed()))
+        } else {
+            window.1 += 1;
+            None
+        };
+        drop(windows);
+        retry_after
+    }
+
+    /// Check a request against the limit, returning the `429` response when
+    /// it is over the limit.
+    fn check(&self, request: &Request) -> Option<Response> {
+        let source = self.source(request);
+        if source.is_some_and(|source| self.allowed.contains(&source)) {
+            return None;
+        }
+        let retry_after = self.acquire(source)?;
+        tracing::warn!(source = ?source, "health route rate limited");
+        let retry_after = retry_after.as_secs().max(1).to_string();
+        Some(
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after)],
+            )
+                .into_response(),
+        )
     }
-    format::json(Health { ok: is_ok })
 }
 
 /// Defines and returns the health-related routes.
 pub fn routes<T: Send + Sync + Clone + 'static>() -> Routes<T> {
     Routes::new().add("/_health", get(health))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum_test::TestServer;
+
+    use super::*;
+    use crate::{
+        storage::{self, Storage},
+        tests_cfg,
+    };
+
+    struct StaticCheck(&'static str, Criticality, HealthStatus);
+
+    #[async_trait]
+    impl HealthCheck<()> for StaticCheck {
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn criticality(&self) -> Criticality {
+            self.1
+        }
+
+        async fn check(&self, _ctx: &AppContext<()>) -> HealthStatus {
+            self.2.clone()
+        }
+    }
+
+    /// A check counting its runs.
+    struct CountingCheck(&'static str, Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl HealthCheck<()> for CountingCheck {
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+
+        async fn check(&self, _ctx: &AppContext<()>) -> HealthStatus {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            HealthStatus::Ok
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_component_is_served_from_cache_within_ttl() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let cheap_runs = Arc::new(AtomicUsize::new(0));
+        let expensive_runs = Arc::new(AtomicUsize::new(0));
+        let registry = HealthRegistry::default()
+            .with_check(CountingCheck("db", cheap_runs.clone()))
+            .with_check(CachedCheck::new(
+                CountingCheck("payments", expensive_runs.clone()),
+                Duration::from_secs(60),
+            ));
+
+        for _ in 0..3 {
+            let report = registry.run(&ctx).await;
+            assert_eq!(report.components["payments"].status, Status::Ok);
+        }
+
+        assert_eq!(cheap_runs.load(Ordering::SeqCst), 3);
+        assert_eq!(expensive_runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn cached_component_is_refreshed_after_ttl() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let runs = Arc::new(AtomicUsize::new(0));
+        let registry = HealthRegistry::default().with_check(CachedCheck::new(
+            CountingCheck("payments", runs.clone()),
+            Duration::from_millis(20),
+        ));
+
+        registry.run(&ctx).await;
+        registry.run(&ctx).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        registry.run(&ctx).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    fn failing(name: &'static str, criticality: Criticality) -> StaticCheck {
+        StaticCheck(name, criticality, HealthStatus::Error("down".to_string()))
+    }
+
+    fn passing(name: &'static str) -> StaticCheck {
+        StaticCheck(name, Criticality::Critical, HealthStatus::Ok)
+    }
+
+    /// Remove the components latencies from a health response, checking they
+    /// were measured.
+    fn without_latency(mut health: serde_json::Value) -> serde_json::Value {
+        for component in health["components"].as_object_mut().unwrap().values_mut() {
+            let latency = component.as_object_mut().unwrap().remove("latency_ms");
+            assert!(latency.is_some_and(|latency| latency.is_u64()));
+        }
+        health
+    }
+
+    #[test]
+    fn component_health_serializes_to_the_documented_contract() {
+        let statuses = [
+            ComponentStatus::Ok,
+            ComponentStatus::Degraded,
+            ComponentStatus::Timeout,
+            ComponentStatus::Error,
+        ];
+        assert_eq!(
+            serde_json::to_value(statuses).unwrap(),
+            serde_json::json!(["ok", "degraded", "timeout", "error"])
+        );
+
+        let component = ComponentHealth {
+            status: ComponentStatus::Degraded,
+            criticality: Criticality::NonCritical,
+            message: Some("slow responses".to_string()),
+            latency_ms: Some(12),
+            details: BTreeMap::new(),
+        };
+        assert_eq!(
+            serde_json::to_value(component).unwrap(),
+            serde_json::json!({
+                "status": "degraded",
+                "criticality": "non_critical",
+                "message": "slow responses",
+                "latency_ms": 12,
+            })
+        );
+
+        let component = ComponentHealth {
+            status: ComponentStatus::Ok,
+            criticality: Criticality::Critical,
+            message: None,
+            latency_ms: None,
+            details: BTreeMap::new(),
+        };
+        assert_eq!(
+            serde_json::to_value(component).unwrap(),
+            serde_json::json!({ "status": "ok", "criticality": "critical" })
+        );
+    }
+
+    #[tokio::test]
+    async fn secrets_check_passes_with_present_secrets() {
+        std::env::set_var("LOCO_TEST_PRESENT_SECRET", "s3cr3t");
+        let ctx = tests_cfg::app::get_app_context().await;
+        let registry =
+            HealthRegistry::default().with_check(SecretsCheck::new(&["LOCO_TEST_PRESENT_SECRET"]));
+
+        let report = registry.run(&ctx).await;
+
+        let component = &report.components["secrets"];
+        assert_eq!(component.status, ComponentStatus::Ok);
+        assert!(component.details.is_empty());
+    }
+
+    #[tokio::test]
+    async fn secrets_check_reports_empty_and_missing_secrets() {
+        std::env::set_var("LOCO_TEST_PRESENT_SECRET_2", "s3cr3t");
+        std::env::set_var("LOCO_TEST_BLANK_SECRET", "  ");
+        std::env::remove_var("LOCO_TEST_MISSING_SECRET");
+        let ctx = tests_cfg::app::get_app_context().await;
+        let registry = HealthRegistry::default().with_check(SecretsCheck::new(&[
+            "LOCO_TEST_PRESENT_SECRET_2",
+            "LOCO_TEST_BLANK_SECRET",
+            "LOCO_TEST_MISSING_SECRET",
+        ]));
+
+        let report = registry.run(&ctx).await;
+
+        assert!(!report.ok);
+        let component = &report.components["secrets"];
+        assert_eq!(component.status, ComponentStatus::Error);
+        assert_eq!(
+            component.message.as_deref(),
+            Some("missing: LOCO_TEST_BLANK_SECRET, LOCO_TEST_MISSING_SECRET")
+        );
+        assert_eq!(
+            component.details["missing"],
+            serde_json::json!(["LOCO_TEST_BLANK_SECRET", "LOCO_TEST_MISSING_SECRET"])
+        );
+    }
+
+    #[cfg(feature = "auth_jwt")]
+    async fn auth_key_component(ctx: &AppContext<()>, check: AuthKeyCheck) -> ComponentHealth {
+        let mut report = HealthRegistry::default().with_check(check).run(ctx).await;
+        report.components.remove("auth_key").unwrap()
+    }
+
+    #[cfg(feature = "auth_jwt")]
+    #[tokio::test]
+    async fn auth_key_check_signs_with_configured_key() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.auth = Some(crate::config::Auth {
+            jwt: Some(crate::config::JWT {
+                location: None,
+                secret: "PqRwLF2rhHe8J22oBeHy".to_string(),
+                expiration: 604_800,
+            }),
+        });
+
+        let component = auth_key_component(&ctx, AuthKeyCheck::default()).await;
+
+        assert_eq!(component.status, ComponentStatus::Ok);
+    }
+
+    #[cfg(feature = "auth_jwt")]
+    #[tokio::test]
+    async fn auth_key_check_reports_missing_and_invalid_keys() {
+        let ctx = tests_cfg::app::get_app_context().await;
+
+        let missing = auth_key_component(&ctx, AuthKeyCheck::default()).await;
+        assert_eq!(missing.status, ComponentStatus::Error);
+        assert_eq!(missing.message.as_deref(), Some("no JWT config found"));
+
+        let invalid = auth_key_component(&ctx, AuthKeyCheck::with_secret("not base64!")).await;
+        assert_eq!(invalid.status, ComponentStatus::Error);
+        assert!(invalid
+            .message
+            .as_deref()
+            .is_some_and(|message| message.starts_with("cannot sign a token:")));
+    }
+
+    #[tokio::test]
+    async fn storage_rw_check_round_trips_an_object() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let registry =
+            HealthRegistry::default().with_check(StorageRwCheck::default().with_prefix("probes/"));
+
+        let report = registry.run(&ctx).await;
+
+        let component = &report.components["storage_rw"];
+        assert_eq!(component.status, ComponentStatus::Ok);
+        assert!(component.latency_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn storage_rw_check_reports_write_failures() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.storage = Storage::single(storage::drivers::null::new()).into();
+        let registry = HealthRegistry::default().with_check(StorageRwCheck::default());
+
+        let report = registry.run(&ctx).await;
+
+        assert!(!report.ok);
+        let component = &report.components["storage_rw"];
+        assert_eq!(component.status, ComponentStatus::Error);
+        assert!(component
+            .message
+            .as_deref()
+            .is_some_and(|message| message.starts_with("write failed:")));
+    }
+
+    fn router(ctx: AppContext<()>) -> axum::Router {
+        let mut router = axum::Router::new();
+        for handler in routes().handlers {
+            router = router.route(&handler.uri, handler.method);
+        }
+        router.with_state(ctx)
+    }
+
+    async fn rate_limited_router(limit: HealthRateLimit) -> axum::Router {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default().with_check(passing("db"));
+        ctx.health.set_rate_limit(Some(limit));
+        router(ctx)
+    }
+
+    /// Requests the health route from the `peer` connection, with the
+    /// `X-Forwarded-For` header when given.
+    async fn get_health_from(
+        router: &axum::Router,
+        peer: &str,
+        forwarded_for: Option<&str>,
+    ) -> Response {
+        let mut request = Request::get("/_health");
+        if let Some(forwarded_for) = forwarded_for {
+            request = request.header("x-forwarded-for", forwarded_for);
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 4000)));
+        router.clone().call(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn health_reports_config_reloads() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default().with_check(passing("db"));
+        let server = TestServer::new(router(ctx.clone())).unwrap();
+
+        let body: serde_json::Value = server.get("/_health").await.json();
+        assert!(body.get("config").is_none());
+
+        ctx.health.record_config_reload("info");
+        ctx.health.record_config_reload("debug");
+        let body: serde_json::Value = server.get("/_health").await.json();
+        assert_eq!(
+            body["config"],
+            serde_json::json!({ "generation": 2, "log_level": "debug" })
+        );
+    }
+
+    #[tokio::test]
+    async fn can_alias_component_names() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(passing("cache"));
+        ctx.health
+            .set_aliases(BTreeMap::from([("db".to_string(), "database".to_string())]))
+            .unwrap();
+        let server = TestServer::new(router(ctx)).unwrap();
+
+        let res = server.get("/_health").await;
+
+        res.assert_status_ok();
+        let body: serde_json::Value = res.json();
+        let components = body["components"].as_object().unwrap();
+        assert_eq!(
+            components.keys().collect::<Vec<_>>(),
+            vec!["cache", "database"]
+        );
+        assert_eq!(components["database"]["status"], "ok");
+    }
+
+    #[test]
+    fn rejects_components_aliased_to_the_same_name() {
+        let registry = HealthRegistry::<()>::default();
+        let aliases = BTreeMap::from([
+            ("db".to_string(), "database".to_string()),
+            ("replica".to_string(), "database".to_string()),
+        ]);
+
+        let err = registry.set_aliases(aliases).unwrap_err();
+        assert!(
+            err.to_string().contains("both aliased to `database`"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_an_alias_taken_by_another_component() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(passing("database"));
+        ctx.health
+            .set_aliases(BTreeMap::from([("db".to_string(), "database".to_string())]))
+            .unwrap();
+        let server = TestServer::new(router(ctx)).unwrap();
+
+        server
+            .get("/_health")
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn can_configure_aliases() {
+        let config: config::Health = serde_json::from_value(serde_json::json!({
+            "aliases": { "db": "database" }
+        }))
+        .unwrap();
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::from_config(&config).unwrap();
+
+        let res = TestServer::new(router(ctx)).unwrap().get("/_health").await;
+
+        let body: serde_json::Value = res.json();
+        assert!(body["components"]["database"].is_object(), "{body}");
+        assert!(body["components"]["db"].is_null(), "{body}");
+    }
+
+    #[tokio::test]
+    async fn excessive_health_requests_are_rate_limited_per_source() {
+        let router = rate_limited_router(HealthRateLimit::new(2, Duration::from_secs(60))).await;
+
+        for _ in 0..2 {
+            let res = get_health_from(&router, "10.0.0.5", None).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        let res = get_health_from(&router, "10.0.0.5", None).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(res.headers().contains_key(header::RETRY_AFTER));
+
+        let res = get_health_from(&router, "10.0.0.6", None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn allowed_sources_are_not_rate_limited() {
+        let limit =
+            HealthRateLimit::new(1, Duration::from_secs(60)).allow("10.0.0.1".parse().unwrap());
+        let router = rate_limited_router(limit).await;
+
+        for _ in 0..3 {
+            let res = get_health_from(&router, "10.0.0.1", None).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn forwarded_for_is_ignored_from_untrusted_peers() {
+        let limit =
+            HealthRateLimit::new(1, Duration::from_secs(60)).allow("10.0.0.1".parse().unwrap());
+        let router = rate_limited_router(limit).await;
+
+        let res = get_health_from(&router, "10.0.0.5", Some("10.0.0.1")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = get_health_from(&router, "10.0.0.5", Some("10.0.0.1")).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = get_health_from(&router, "10.0.0.5", Some("10.0.0.7")).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn trusted_proxies_set_the_source() {
+        let limit = HealthRateLimit::new(1, Duration::from_secs(60))
+            .allow("10.0.0.1".parse().unwrap())
+            .trust_proxy("10.0.0.2".parse().unwrap());
+        let router = rate_limited_router(limit).await;
+
+        let res = get_health_from(&router, "10.0.0.2", Some("203.0.113.1")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = get_health_from(&router, "10.0.0.2", Some("203.0.113.1")).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = get_health_from(&router, "10.0.0.2", Some("203.0.113.2")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // only the address appended by the trusted proxy counts
+        let res = get_health_from(&router, "10.0.0.2", Some("10.0.0.1, 203.0.113.1")).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        for _ in 0..2 {
+            let res = get_health_from(&router, "10.0.0.2", Some("10.0.0.1")).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn forwarded_for_is_the_source_without_a_peer() {
+        let limit =
+            HealthRateLimit::new(1, Duration::from_secs(60)).allow("10.0.0.1".parse().unwrap());
+        let router = rate_limited_router(limit).await;
+        let get_health = |forwarded_for: &'static str| {
+            let request = Request::get("/_health")
+                .header("x-forwarded-for", forwarded_for)
+                .body(Body::empty())
+                .unwrap();
+            router.clone().call(request)
+        };
+
+        for _ in 0..2 {
+            let res = get_health("10.0.0.1").await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+        let res = get_health("203.0.113.1").await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = get_health("203.0.113.1").await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = get_health("203.0.113.2").await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn can_configure_rate_limit() {
+        let config: config::Health = serde_json::from_value(serde_json::json!({
+            "rate_limit": { "max_requests": 1, "window": 60_000, "allow": ["10.0.0.1"] }
+        }))
+        .unwrap();
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::from_config(&config).unwrap();
+        for name in ctx.health.names() {
+            assert!(ctx.health.unregister_check(&name));
+        }
+        let router = router(ctx);
+
+        let res = get_health_from(&router, "10.0.0.5", None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = get_health_from(&router, "10.0.0.5", None).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = get_health_from(&router, "10.0.0.1", None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn can_register_checks_at_runtime() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default().with_check(passing("db"));
+        let registry = ctx.health.clone();
+        let server = TestServer::new(router(ctx)).unwrap();
+
+        let res = server.get("/_health").await;
+        assert!(res.json::<serde_json::Value>()["components"]["flags"].is_null());
+
+        registry.register_check(failing("flags", Criticality::Critical));
+        let res = server.get("/_health").await;
+        assert_eq!(
+            without_latency(res.json()),
+            serde_json::json!({
+                "ok": false,
+                "status": "error",
+                "components": {
+                    "db": { "status": "ok", "criticality": "critical" },
+                    "flags": { "status": "error", "criticality": "critical", "message": "down" },
+                },
+            })
+        );
+
+        assert!(registry.unregister_check("flags"));
+        assert!(!registry.unregister_check("flags"));
+        assert_eq!(registry.names(), vec!["db"]);
+        let res = server.get("/_health").await;
+        assert_eq!(res.json::<serde_json::Value>()["ok"], true);
+    }
+
+    #[test]
+    fn registering_replaces_checks_with_the_same_name() {
+        let registry = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(failing("db", Criticality::Critical));
+
+        assert_eq!(registry.names(), vec!["db"]);
+    }
+
+    /// A check tracking how many checks run at the same time.
+    struct ConcurrencyCheck {
+        name: String,
+        running: Arc<AtomicUsize>,
+        max_running: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl HealthCheck<()> for ConcurrencyCheck {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn check(&self, _ctx: &AppContext<()>) -> HealthStatus {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            HealthStatus::Ok
+        }
+    }
+
+    async fn max_running_checks(registry: HealthRegistry<()>) -> usize {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        for idx in 0..6 {
+            registry.register_check(ConcurrencyCheck {
+                name: format!("check-{idx}"),
+                running: running.clone(),
+                max_running: max_running.clone(),
+            });
+        }
+
+        let report = registry.run(&ctx).await;
+
+        assert!(report.ok);
+        assert_eq!(report.components.len(), 6);
+        max_running.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn runs_checks_concurrently() {
+        assert_eq!(max_running_checks(HealthRegistry::default()).await, 6);
+    }
+
+    #[tokio::test]
+    async fn can_configure_concurrency_limit() {
+        let config = config::Health {
+            concurrency_limit: NonZeroUsize::new(3),
+            ..Default::default()
+        };
+        let registry = HealthRegistry::from_config(&config).unwrap();
+        for name in registry.names() {
+            assert!(registry.unregister_check(&name));
+        }
+
+        assert_eq!(max_running_checks(registry).await, 3);
+    }
+
+    #[test]
+    fn rejects_a_zero_concurrency_limit() {
+        let err = serde_yaml::from_str::<config::Health>("concurrency_limit: 0").unwrap_err();
+
+        assert!(err.to_string().contains("nonzero"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn can_bound_concurrent_checks() {
+        let registry =
+            HealthRegistry::default().with_concurrency_limit(NonZeroUsize::new(2).unwrap());
+
+        assert_eq!(max_running_checks(registry).await, 2);
+    }
+
+    /// A check that takes a while to complete.
+    struct SlowCheck(&'static str, Criticality, Duration);
+
+    #[async_trait]
+    impl HealthCheck<()> for SlowCheck {
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+
+        fn criticality(&self) -> Criticality {
+            self.1
+        }
+
+        async fn check(&self, _ctx: &AppContext<()>) -> HealthStatus {
+            tokio::time::sleep(self.2).await;
+            HealthStatus::Ok
+        }
+    }
+
+    #[tokio::test]
+    async fn deadline_reports_partial_results() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let registry = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(SlowCheck(
+                "cache",
+                Criticality::Critical,
+                Duration::from_millis(5),
+            ))
+            .with_check(SlowCheck(
+                "search",
+                Criticality::Critical,
+                Duration::from_secs(60),
+            ))
+            .with_deadline(Duration::from_millis(100));
+
+        let started = Instant::now();
+        let report = registry.run(&ctx).await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(report.components["db"].status, Status::Ok);
+        assert_eq!(report.components["cache"].status, Status::Ok);
+        let search = &report.components["search"];
+        assert_eq!(search.status, Status::Timeout);
+        assert!(search
+            .message
+            .as_deref()
+            .is_some_and(|message| message.starts_with("timed out after")));
+        assert!(!report.ok);
+        assert_eq!(report.status, Status::Error);
+    }
+
+    #[tokio::test]
+    async fn non_critical_timeout_degrades() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let registry = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(SlowCheck(
+                "search",
+                Criticality::NonCritical,
+                Duration::from_secs(60),
+            ))
+            .with_deadline(Duration::from_millis(50));
+
+        let report = registry.run(&ctx).await;
+
+        assert!(report.ok);
+        assert_eq!(report.status, Status::Degraded);
+        assert_eq!(report.components["search"].status, Status::Timeout);
+    }
+
+    #[cfg(feature = "with-db")]
+    async fn replication_lag(lag: f64) -> ComponentHealth {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let check = ReplicationLagCheck::new(Duration::from_secs(5))
+            .with_query(&format!("SELECT {lag} AS lag_seconds"));
+        let mut report = HealthRegistry::default().with_check(check).run(&ctx).await;
+        report.components.remove("replication_lag").unwrap()
+    }
+
+    #[cfg(feature = "with-db")]
+    #[tokio::test]
+    async fn replication_lag_within_threshold_is_ok() {
+        let component = replication_lag(1.5).await;
+
+        assert_eq!(component.status, Status::Ok);
+        assert_eq!(
+            component.details.get("replication_lag_seconds"),
+            Some(&serde_json::json!(1.5))
+        );
+    }
+
+    #[cfg(feature = "with-db")]
+    #[tokio::test]
+    async fn replication_lag_over_threshold_fails() {
+        let component = replication_lag(7.5).await;
+
+        assert_eq!(component.status, Status::Error);
+        assert_eq!(
+            component.message.as_deref(),
+            Some("replication lag of 7.5s exceeds 5s")
+        );
+        assert_eq!(
+            component.details.get("replication_lag_seconds"),
+            Some(&serde_json::json!(7.5))
+        );
+    }
+
+    #[cfg(feature = "with-db")]
+    #[tokio::test]
+    async fn replication_lag_needs_a_query_on_sqlite() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let check = ReplicationLagCheck::new(Duration::from_secs(5));
+
+        let (status, details) = HealthCheck::<()>::check_with_details(&check, &ctx).await;
+
+        assert_eq!(
+            status,
+            HealthStatus::Error(
+                "no replication lag query for Sqlite, set one with `with_query`".to_string()
+            )
+        );
+        assert!(details.is_empty());
+    }
+
+    fn internal_router() -> axum::Router {
+        axum::Router::new()
+            .route("/healthy", get(|| async { "ok" }))
+            .route(
+                "/failing",
+                get(|| async { axum::http::StatusCode::INTERNAL_SERVER_ERROR }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "late"
+                }),
+            )
+    }
+
+    async fn self_check(path: &str) -> ComponentHealth {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let check = SelfCheck::new(internal_router(), path, Duration::from_millis(100)).unwrap();
+        let mut report = HealthRegistry::default().with_check(check).run(&ctx).await;
+        report.components.remove("self_check").unwrap()
+    }
+
+    #[tokio::test]
+    async fn self_check_passes_on_healthy_route() {
+        let component = self_check("/healthy").await;
+
+        assert_eq!(component.status, Status::Ok);
+    }
+
+    #[tokio::test]
+    async fn self_check_fails_on_failing_route() {
+        let component = self_check("/failing").await;
+
+        assert_eq!(component.status, Status::Error);
+        assert_eq!(
+            component.message.as_deref(),
+            Some("/failing responded with 500 Internal Server Error")
+        );
+        let component = self_check("/missing").await;
+        assert_eq!(component.status, Status::Error);
+    }
+
+    #[tokio::test]
+    async fn self_check_fails_past_deadline() {
+        let component = self_check("/slow").await;
+
+        assert_eq!(component.status, Status::Error);
+        assert_eq!(
+            component.message.as_deref(),
+            Some("/slow did not respond within 100ms")
+        );
+    }
+
+    #[test]
+    fn self_check_cannot_target_health_routes() {
+        assert!(SelfCheck::new(internal_router(), "/_health", Duration::from_secs(1)).is_err());
+        assert!(SelfCheck::new(internal_router(), "/_readiness", Duration::from_secs(1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn informational_failures_do_not_affect_status() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let checks = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(failing("flags", Criticality::Informational));
+
+        let report = checks.run(&ctx).await;
+
+        assert!(report.ok);
+        assert_eq!(report.status, Status::Ok);
+        assert_eq!(report.components["flags"].status, Status::Error);
+        assert_eq!(report.components["flags"].message.as_deref(), Some("down"));
+    }
+
+    #[tokio::test]
+    async fn non_critical_failures_degrade_status() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let checks = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(failing("search", Criticality::NonCritical))
+            .with_check(failing("flags", Criticality::Informational));
+
+        let report = checks.run(&ctx).await;
+
+        assert!(report.ok);
+        assert_eq!(report.status, Status::Degraded);
+    }
+
+    #[tokio::test]
+    async fn critical_failures_make_status_error() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let checks = HealthRegistry::default()
+            .with_check(failing("db", Criticality::Critical))
+            .with_check(failing("search", Criticality::NonCritical));
+
+        let report = checks.run(&ctx).await;
+
+        assert!(!report.ok);
+        assert_eq!(report.status, Status::Error);
+    }
+
+    #[tokio::test]
+    async fn can_serve_health_components() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default()
+            .with_check(passing("db"))
+            .with_check(failing("flags", Criticality::Informational));
+        let server = TestServer::new(router(ctx)).unwrap();
+
+        let res = server.get("/_health").await;
+
+        res.assert_status_ok();
+        assert_eq!(
+            without_latency(res.json()),
+            serde_json::json!({
+                "ok": true,
+                "status": "ok",
+                "components": {
+                    "db": { "status": "ok", "criticality": "critical" },
+                    "flags": { "status": "error", "criticality": "informational", "message": "down" },
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Renders handler errors with a custom JSON body.
+//!
+//! When an [`crate::Error`] is converted into a response, its [`ErrorDetail`]
+//! is kept in the response extensions. This middleware hands it to the
+//! [`crate::app::Hooks::error_body`] serializer and replaces the body with
+//! the returned JSON, keeping the default body when the serializer returns
+//! `None`.
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+    Router,
+};
+
+use crate::controller::ErrorDetail;
+
+/// Serializes an error into a JSON body, or returns `None` to keep the default
+/// body.
+pub type ErrorBodySerializer = fn(StatusCode, &ErrorDetail) -> Option<serde_json::Value>;
+
+/// Layers the given serializer on the router.
+pub fn layer(router: Router, serializer: ErrorBodySerializer) -> Router {
+    router.layer(axum::middleware::map_response(
+        move |response: Response| async move { render(response, serializer) },
+    ))
+}
+
+fn render(response: Response, serializer: ErrorBodySerializer) -> Response {
+    let Some(body) = response
+        .extensions()
+        .get::<ErrorDetail>()
+        .and_then(|detail| serializer(response.status(), detail))
+    else {
+        return response;
+    };
+
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+    );
+    Response::from_parts(parts, Body::from(body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum_test::TestServer;
+    use serde_json::json;
+
+    use super::*;
+    use crate::Error;
+
+    fn errors_array(status: StatusCode, detail: &ErrorDetail) -> Option<serde_json::Value> {
+        Some(json!({
+            "errors": [{
+                "status": status.as_u16(),
+                "code": detail.error,
+                "title": detail.description,
+            }]
+        }))
+    }
+
+    fn app(serializer: ErrorBodySerializer) -> Router {
+        let router = Router::new()
+            .route("/missing", get(|| async { Err::<(), _>(Error::NotFound) }))
+            .route("/ok", get(|| async { "ok" }));
+        layer(router, serializer)
+    }
+
+    #[tokio::test]
+    async fn can_use_custom_error_body() {
+        let server = TestServer::new(app(errors_array)).unwrap();
+
+        let res = server.get("/missing").await;
+
+        res.assert_status_not_found();
+        assert_eq!(
+            res.header(header::CONTENT_TYPE),
+            mime::APPLICATION_JSON.as_ref()
+        );
+        res.assert_json(&json!({
+            "errors": [{
+                "status": 404,
+                "code": "not_found",
+                "title": "Resource was not found",
+            }]
+        }));
+        server.get("/ok").await.assert_text("ok");
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_default_error_body() {
+        let server = TestServer::new(app(|_, _| None)).unwrap();
+
+        let res = server.get("/missing").await;
+
+        res.assert_status_not_found();
+        res.assert_json(&json!({
+            "error": "not_found",
+            "description": "Resource was not found",
+        }));
+    }
+}
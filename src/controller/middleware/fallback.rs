@@ -0,0 +1,107 @@
+//! Answers unmatched routes and method mismatches with a JSON error body.
+//!
+//! Unknown paths get a `404 Not Found` and known paths requested with an
+//! unsupported method a `405 Method Not Allowed`, both with the same
+//! [`ErrorDetail`] body as the handler errors. The detail is kept in the
+//! response extensions, so a custom [`crate::app::Hooks::error_body`]
+//! serializer applies to them too.
+use axum::{
+    http::{header, StatusCode},
+    response::Response,
+    Router,
+};
+
+use crate::controller::{error_response, ErrorDetail};
+
+/// Layers the JSON fallback on the router. Must be layered after all the
+/// routes are added, and before the middlewares so that the fallback
+/// responses go through them.
+pub fn layer<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    router
+        .layer(axum::middleware::map_response(method_not_allowed))
+        .fallback(not_found)
+}
+
+async fn not_found() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        ErrorDetail::new("not_found", "Resource was not found"),
+    )
+}
+
+/// Replaces the empty body of the router method mismatch responses, keeping
+/// the `Allow` header.
+async fn method_not_allowed(response: Response) -> Response {
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED
+        || response.extensions().get::<ErrorDetail>().is_some()
+    {
+        return response;
+    }
+
+    let mut json = error_response(
+        StatusCode::METHOD_NOT_ALLOWED,
+        ErrorDetail::new(
+            "method_not_allowed",
+            "Method is not allowed for this resource",
+        ),
+    );
+    if let Some(allow) = response.headers().get(header::ALLOW) {
+        json.headers_mut().insert(header::ALLOW, allow.clone());
+    }
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum_test::TestServer;
+    use serde_json::json;
+
+    use super::*;
+    use crate::controller::middleware::error_body;
+
+    fn app() -> Router {
+        layer(Router::new().route("/users", get(|| async { "users" })))
+    }
+
+    #[tokio::test]
+    async fn unknown_path_is_json_not_found() {
+        let server = TestServer::new(app()).unwrap();
+
+        let res = server.get("/nope").await;
+
+        res.assert_status_not_found();
+        res.assert_json(&json!({
+            "error": "not_found",
+            "description": "Resource was not found",
+        }));
+        server.get("/users").await.assert_text("users");
+    }
+
+    #[tokio::test]
+    async fn wrong_method_is_json_method_not_allowed() {
+        let server = TestServer::new(app()).unwrap();
+
+        let res = server.post("/users").await;
+
+        res.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+        assert!(res.header(header::ALLOW).to_str().unwrap().contains("GET"));
+        res.assert_json(&json!({
+            "error": "method_not_allowed",
+            "description": "Method is not allowed for this resource",
+        }));
+    }
+
+    #[tokio::test]
+    async fn fallback_uses_custom_error_body() {
+        let router = error_body::layer(app(), |status, detail| {
+            Some(json!({ "errors": [{ "status": status.as_u16(), "code": detail.error }] }))
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let res = server.get("/nope").await;
+
+        res.assert_status_not_found();
+        res.assert_json(&json!({ "errors": [{ "status": 404, "code": "not_found" }] }));
+    }
+}
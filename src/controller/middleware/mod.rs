@@ -1,4 +1,8 @@
 #[cfg(all(feature = "auth_jwt", feature = "with-db"))]
 pub mod auth;
+pub mod error_body;
 pub mod etag;
+pub mod fallback;
 pub mod format;
+pub mod request_id;
+pub mod request_log;
@@ -0,0 +1,304 @@
+//! Emits a structured access log line for every request.
+//!
+//! The log line is emitted as a `tracing` event with the `request_completed`
+//! message, inside the request span created by the logger middleware. The
+//! fields, skipped paths, headers logging and the slow request threshold are
+//! configured with [`config::RequestLogMiddleware`].
+use std::{
+    collections::BTreeMap,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::{MatchedPath, Request},
+    http::HeaderMap,
+    response::Response,
+};
+use futures_util::future::BoxFuture;
+use hyper::header::{CONTENT_LENGTH, USER_AGENT};
+use tower::{Layer, Service};
+
+use super::request_id::RequestId;
+use crate::config::{self, RequestLogField, RequestLogLevel};
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Clone)]
+pub struct RequestLogLayer {
+    config: config::RequestLogMiddleware,
+}
+
+impl RequestLogLayer {
+    #[must_use]
+    pub fn new(config: config::RequestLogMiddleware) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> Layer<S> for RequestLogLayer {
+    type Service = RequestLogMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestLogMiddleware {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestLogMiddleware<S> {
+    inner: S,
+    config: config::RequestLogMiddleware,
+}
+
+impl<S> Service<Request<Body>> for RequestLogMiddleware<S>
+where
+    S: Service<Request, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let path = request.extensions().get::<MatchedPath>().map_or_else(
+            || request.uri().path().to_string(),
+            |matched| matched.as_str().to_string(),
+        );
+
+        if self.config.skip_paths.iter().any(|skip| skip == &path) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let config = self.config.clone();
+        let entry = RequestEntry {
+            method: request.method().to_string(),
+            path,
+            request_id: request
+                .extensions()
+                .get::<RequestId>()
+                .map(ToString::to_string),
+            user_agent: request
+                .headers()
+                .get(USER_AGENT)
+                .and_then(|h| h.to_str().ok())
+                .map(ToString::to_string),
+            request_headers: config
+                .log_headers
+                .then(|| redact_headers(request.headers(), &config.redact_headers)),
+        };
+
+        let started = Instant::now();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await?;
+            entry.log(&config, &response, started.elapsed());
+            Ok(response)
+        })
+    }
+}
+
+/// The request details captured before handing the request to the handler.
+struct RequestEntry {
+    method: String,
+    path: String,
+    request_id: Option<String>,
+    user_agent: Option<String>,
+    request_headers: Option<BTreeMap<String, String>>,
+}
+
+impl RequestEntry {
+    fn log(self, config: &config::RequestLogMiddleware, response: &Response, latency: Duration) {
+        let has = |field: RequestLogField| config.fields.contains(&field);
+
+        let method = has(RequestLogField::Method).then_some(self.method);
+        let path = has(RequestLogField::Path).then_some(self.path);
+        let status = has(RequestLogField::Status).then(|| response.status().as_u16());
+        let latency_ms = has(RequestLogField::Latency)
+            .then(|| u64::try_from(latency.as_millis()).unwrap_or(u64::MAX));
+        let bytes = has(RequestLogField::Bytes)
+            .then(|| response_bytes(response))
+            .flatten();
+        let request_id = has(RequestLogField::RequestId)
+            .then_some(self.request_id)
+            .flatten();
+        let user_agent = has(RequestLogField::UserAgent)
+            .then_some(self.user_agent)
+            .flatten();
+        let request_headers = self.request_headers.map(|h| format!("{h:?}"));
+        let response_headers = config.log_headers.then(|| {
+            format!(
+                "{:?}",
+                redact_headers(response.headers(), &config.redact_headers)
+            )
+        });
+
+        let slow = config
+            .slow_threshold
+            .is_some_and(|threshold| latency >= Duration::from_millis(threshold));
+
+        macro_rules! log_request {
+            ($level:ident) => {
+                tracing::$level!(
+                    http.method = method,
+                    http.path = path,
+                    http.status = status,
+                    latency_ms = latency_ms,
+                    bytes = bytes,
+                    request_id = request_id,
+                    http.user_agent = user_agent,
+                    http.request_headers = request_headers,
+                    http.response_headers = response_headers,
+                    slow = slow,
+                    "request_completed"
+                )
+            };
+        }
+
+        match (slow, config.level) {
+            (true, _) => log_request!(warn),
+            (false, RequestLogLevel::Trace) => log_request!(trace),
+            (false, RequestLogLevel::Debug) => log_request!(debug),
+            (false, RequestLogLevel::Info) => log_request!(info),
+        }
+    }
+}
+
+/// Returns the response body size, when known up front.
+fn response_bytes(response: &Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse().ok())
+        .or_else(|| response.body().size_hint().exact())
+}
+
+/// Collects the given headers, replacing the values of the redacted ones.
+fn redact_headers(headers: &HeaderMap, redact: &[String]) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if redact.iter().any(|r| r.eq_ignore_ascii_case(name.as_str())) {
+                REDACTED.to_string()
+            } else {
+                value.to_str().unwrap_or_default().to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Router};
+    use axum_test::TestServer;
+
+    use super::*;
+    use crate::tests_cfg::capture::Capture;
+
+    fn app(config: config::RequestLogMiddleware) -> Router {
+        Router::new()
+            .route("/users/:id", get(|| async { "user" }))
+            .route("/_health", get(|| async { "ok" }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    "slow"
+                }),
+            )
+            .layer(RequestLogLayer::new(config))
+    }
+
+    #[tokio::test]
+    async fn can_log_request() {
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+
+        let config = config::RequestLogMiddleware {
+            log_headers: true,
+            ..Default::default()
+        };
+        let server = TestServer::new(app(config)).unwrap();
+        server
+            .get("/users/1")
+            .add_header(
+                "authorization".parse().unwrap(),
+                "Bearer secret".parse().unwrap(),
+            )
+            .await;
+
+        let events = capture.events_with_message("request_completed");
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.level, tracing::Level::INFO);
+        assert_eq!(event.field("http.method"), Some("GET"));
+        assert_eq!(event.field("http.path"), Some("/users/:id"));
+        assert_eq!(event.field("http.status"), Some("200"));
+        assert_eq!(event.field("bytes"), Some("4"));
+        assert!(event.field("latency_ms").is_some());
+        let headers = event.field("http.request_headers").unwrap();
+        assert!(headers.contains(REDACTED));
+        assert!(!headers.contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn can_log_request_at_configured_level() {
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+
+        let config = config::RequestLogMiddleware {
+            level: RequestLogLevel::Debug,
+            ..Default::default()
+        };
+        let server = TestServer::new(app(config)).unwrap();
+        server.get("/users/1").await;
+
+        let events = capture.events_with_message("request_completed");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, tracing::Level::DEBUG);
+    }
+
+    #[tokio::test]
+    async fn can_skip_paths() {
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+
+        let server = TestServer::new(app(config::RequestLogMiddleware::default())).unwrap();
+        server.get("/_health").await;
+
+        assert!(capture.events_with_message("request_completed").is_empty());
+    }
+
+    #[tokio::test]
+    async fn can_log_slow_request_as_warn() {
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+
+        let config = config::RequestLogMiddleware {
+            slow_threshold: Some(10),
+            fields: vec![RequestLogField::Path, RequestLogField::Status],
+            ..Default::default()
+        };
+        let server = TestServer::new(app(config)).unwrap();
+        server.get("/slow").await;
+
+        let events = capture.events_with_message("request_completed");
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.level, tracing::Level::WARN);
+        assert_eq!(event.field("slow"), Some("true"));
+        assert_eq!(event.field("http.path"), Some("/slow"));
+        assert_eq!(event.field("http.method"), None);
+        assert_eq!(event.field("latency_ms"), None);
+    }
+}
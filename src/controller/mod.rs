@@ -90,11 +90,12 @@ mod backtrace;
 pub mod channels;
 mod describe;
 pub mod format;
-#[cfg(feature = "with-db")]
-mod health;
+pub mod health;
 pub mod middleware;
+pub mod pagination;
 mod ping;
 mod routes;
+pub mod tasks;
 pub mod views;
 
 /// Create an unauthorized error with a specified message.
@@ -140,7 +141,7 @@ pub fn bad_request<T: Into<String>, U>(msg: T) -> Result<U> {
 pub fn not_found<T>() -> Result<T> {
     Err(Error::NotFound)
 }
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 /// Structure representing details about an error.
 pub struct ErrorDetail {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -169,6 +170,14 @@ impl ErrorDetail {
     }
 }
 
+/// The JSON error response of a status and detail. The detail is kept in the
+/// response extensions for the [`crate::app::Hooks::error_body`] serializer.
+pub(crate) fn error_response(status: StatusCode, detail: ErrorDetail) -> Response {
+    let mut response = (status, Json(&detail)).into_response();
+    response.extensions_mut().insert(detail);
+    response
+}
+
 #[derive(Debug, FromRequest)]
 #[from_request(via(axum::Json), rejection(Error))]
 pub struct Json<T>(pub T);
@@ -236,6 +245,7 @@ impl IntoResponse for Error {
             ),
         };
 
-        (public_facing_error.0, Json(public_facing_error.1)).into_response()
+        let (status, detail) = public_facing_error;
+        error_response(status, detail)
     }
 }
@@ -0,0 +1,364 @@
+//! Pagination query extractor
+//!
+//! Reads the page and page size from the query string, using the parameter
+//! names, default page size and maximum page size configured in
+//! [`config::Pagination`].
+//!
+//! # Example:
+//!
+//! ```rust
+//! use loco_rs::prelude::*;
+//!
+//! async fn list(pagination: Pagination) -> Result<Response> {
+//!     let items: Vec<String> = vec![];
+//!     format::paginated(items, &pagination, 0)
+//! }
+//! ```
+//!
+//! With a database, select the requested page of a query with
+//! [`crate::model::query::PaginateExt::page`]:
+//!
+//! ```rust,ignore
+//! let total_items = Entity::find().count(&ctx.db).await?;
+//! let items = Entity::find().page(&pagination).all(&ctx.db).await?;
+//! format::paginated(items, &pagination, total_items)
+//! ```
+//!
+//! The extractor also converts into a
+//! [`crate::model::query::PaginationQuery`] for
+//! [`crate::model::query::fetch_page`].
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+};
+use serde::{Deserialize, Serialize};
+
+use super::ErrorDetail;
+use crate::{app::AppContext, config, errors::Error};
+
+/// The requested page, extracted from the query string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Pagination {
+    /// The requested page, starting from 1
+    pub page: u64,
+    /// The number of items in a page
+    pub per_page: u64,
+}
+
+/// The pagination details of a paginated response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PaginationMeta {
+    pub page: u64,
+    pub per_page: u64,
+    pub total_items: u64,
+    pub total_pages: u64,
+}
+
+/// A paginated response envelope.
+#[derive(Debug, Serialize)]
+pub struct Paginated<T> {
+    pub results: Vec<T>,
+    pub pagination: PaginationMeta,
+}
+
+/// A cursor paginated response envelope, see
+/// [`crate::controller::format::cursor_paginated`].
+#[derive(Debug, Serialize)]
+pub struct CursorPaginated<T> {
+    pub results: Vec<T>,
+    /// The opaque cursor of the next page, absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl Pagination {
+    /// Build a pagination from raw query values, falling back to the
+    /// configured defaults. A page size larger than the configured maximum
+    /// is clamped to the maximum.
+    ///
+    /// # Errors
+    ///
+    /// When a value is not a number, the page or page size is lower than 1, or
+    /// the page is too far for its offset to fit in a `u64`
+    pub fn from_params(
+        config: &config::Pagination,
+        page: Option<&str>,
+        per_page: Option<&str>,
+    ) -> Result<Self, Error> {
+        let page = parse_param(&config.page_param, page)?.unwrap_or(1);
+        let per_page = parse_param(&config.per_page_param, per_page)?
+            .unwrap_or(config.default_per_page)
+            .min(config.max_per_page);
+
+        if (page - 1).checked_mul(per_page).is_none() {
+            return Err(invalid_param(&config.page_param, "is too large"));
+        }
+        Ok(Self { page, per_page })
+    }
+
+    /// The number of items to skip.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.page.saturating_sub(1).saturating_mul(self.per_page)
+    }
+
+    /// The maximum number of items to return.
+    #[must_use]
+    pub fn limit(&self) -> u64 {
+        self.per_page
+    }
+
+    /// Select the requested page of `query`, with an offset and a limit.
+    #[cfg(feature = "with-db")]
+    #[must_use]
+    pub fn apply<Q: sea_orm::QuerySelect>(&self, query: Q) -> Q {
+        query.offset(self.offset()).limit(self.limit())
+    }
+
+    /// Compute the pagination details for the given total number of items.
+    #[must_use]
+    pub fn meta(&self, total_items: u64) -> PaginationMeta {
+        PaginationMeta {
+            page: self.page,
+            per_page: self.per_page,
+            total_items,
+            // `u64::div_ceil` without overflowing, it needs a newer MSRV
+            total_pages: total_items / self.per_page + u64::from(total_items % self.per_page != 0),
+        }
+    }
+
+    /// Wrap a page of results in a [`Paginated`] envelope.
+    #[must_use]
+    pub fn paginated<T>(&self, results: Vec<T>, total_items: u64) -> Paginated<T> {
+        Paginated {
+            results,
+            pagination: self.meta(total_items),
+        }
+    }
+}
+
+/// Parse a single pagination query parameter.
+fn parse_param(name: &str, value: Option<&str>) -> Result<Option<u64>, Error> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+
+    match value.parse::<u64>() {
+        Ok(value) if value >= 1 => Ok(Some(value)),
+        _ => Err(invalid_param(name, "must be a number greater than 0")),
+    }
+}
+
+fn invalid_param(name: &str, reason: &str) -> Error {
+    Error::CustomError(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorDetail::new(
+            "invalid_pagination".to_string(),
+            format!("`{name}` {reason}"),
+        ),
+    )
+}
+
+#[async_trait]
+impl<T> FromRequestParts<AppContext<T>> for Pagination
+where
+    T: Send + Sync + Clone,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppContext<T>,
+    ) -> Result<Self, Self::Rejection> {
+        let config = &state.config.server.pagination;
+        let Query(params) = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+            .map_err(|err| Error::BadRequest(err.to_string()))?;
+
+        Self::from_params(
+            config,
+            params.get(&config.page_param).map(String::as_str),
+            params.get(&config.per_page_param).map(String::as_str),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Router};
+    use axum_test::TestServer;
+
+    use super::*;
+    use crate::{controller::format, tests_cfg, Result};
+
+    async fn server() -> TestServer {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.config.server.pagination.max_per_page = 50;
+
+        let router = Router::new()
+            .route(
+                "/",
+                get(|pagination: Pagination| async move { format::json(pagination) }),
+            )
+            .with_state(ctx);
+        TestServer::new(router).unwrap()
+    }
+
+    #[tokio::test]
+    async fn can_use_defaults() {
+        let res = server().await.get("/").await;
+
+        res.assert_status_ok();
+        res.assert_json(&Pagination {
+            page: 1,
+            per_page: 25,
+        });
+    }
+
+    #[tokio::test]
+    async fn can_clamp_per_page() {
+        let res = server()
+            .await
+            .get("/")
+            .add_query_param("page", 2)
+            .add_query_param("per_page", 100_000)
+            .await;
+
+        res.assert_status_ok();
+        res.assert_json(&Pagination {
+            page: 2,
+            per_page: 50,
+        });
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_input() {
+        let server = server().await;
+
+        for (param, value) in [("page", "abc"), ("page", "0"), ("per_page", "-1")] {
+            let res = server.get("/").add_query_param(param, value).await;
+            res.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+            res.assert_json(&serde_json::json!({
+                "error": "invalid_pagination",
+                "description": format!("`{param}` must be a number greater than 0"),
+            }));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_a_page_whose_offset_overflows() {
+        let server = server().await;
+
+        let res = server
+            .get("/")
+            .add_query_param("page", u64::MAX.to_string())
+            .await;
+        res.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        res.assert_json(&serde_json::json!({
+            "error": "invalid_pagination",
+            "description": "`page` is too large",
+        }));
+
+        let pagination = Pagination {
+            page: u64::MAX,
+            per_page: 2,
+        };
+        assert_eq!(pagination.offset(), u64::MAX);
+    }
+
+    #[test]
+    fn can_compute_last_partial_page() {
+        let pagination = Pagination {
+            page: 3,
+            per_page: 10,
+        };
+
+        assert_eq!(pagination.offset(), 20);
+        assert_eq!(pagination.limit(), 10);
+        assert_eq!(
+            pagination.meta(25),
+            PaginationMeta {
+                page: 3,
+                per_page: 10,
+                total_items: 25,
+                total_pages: 3,
+            }
+        );
+        assert_eq!(pagination.meta(0).total_pages, 0);
+        assert_eq!(pagination.meta(u64::MAX).total_pages, u64::MAX / 10 + 1);
+    }
+
+    #[tokio::test]
+    async fn can_respond_with_paginated_envelope() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let router = Router::new()
+            .route(
+                "/",
+                get(|pagination: Pagination| async move {
+                    format::paginated(vec!["a", "b"], &pagination, 12)
+                }),
+            )
+            .with_state(ctx);
+        let server = TestServer::new(router).unwrap();
+
+        let res = server
+            .get("/")
+            .add_query_param("page", 6)
+            .add_query_param("per_page", 2)
+            .await;
+
+        res.assert_status_ok();
+        res.assert_json(&serde_json::json!({
+            "results": ["a", "b"],
+            "pagination": {
+                "page": 6,
+                "per_page": 2,
+                "total_items": 12,
+                "total_pages": 6,
+            },
+        }));
+    }
+
+    #[test]
+    fn can_use_configured_param_names() -> Result<()> {
+        let config = config::Pagination {
+            page_param: "p".to_string(),
+            per_page_param: "size".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Pagination::from_params(&config, Some("4"), None)?,
+            Pagination {
+                page: 4,
+                per_page: 25
+            }
+        );
+        let err = Pagination::from_params(&config, None, Some("x")).unwrap_err();
+        assert!(
+            matches!(err, Error::CustomError(status, _) if status == StatusCode::UNPROCESSABLE_ENTITY)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "with-db")]
+    #[test]
+    fn can_select_the_page_of_a_query() {
+        use sea_orm::{DbBackend, EntityTrait, QueryTrait};
+
+        use crate::{model::query::PaginateExt, tests_cfg::db::test_db};
+
+        let pagination = Pagination {
+            page: 3,
+            per_page: 10,
+        };
+
+        let sql = test_db::Entity::find()
+            .page(&pagination)
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.ends_with("LIMIT 10 OFFSET 20"), "{sql}");
+    }
+}
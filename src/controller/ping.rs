@@ -2,11 +2,16 @@
 //! reporting. These routes are commonly used to monitor the health of the
 //! application and its dependencies.
 
-use axum::{response::Response, routing::get};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    response::Response,
+    routing::get,
+};
 use serde::Serialize;
 
 use super::{format, routes::Routes};
-use crate::Result;
+use crate::{app::AppContext, Result};
 
 /// Represents the health status of the application.
 #[derive(Serialize)]
@@ -19,7 +24,207 @@ async fn ping() -> Result<Response> {
     format::json(Health { ok: true })
 }
 
+/// Check the application is ready to receive traffic. Fails as soon as the
+/// application starts shutting down, so load balancers stop sending requests
+/// while in-flight ones are drained, and while a critical component of the
+/// [`AppContext::health`] registry is failing. Rate limited like the health
+/// endpoint, see [`crate::controller::health::HealthRateLimit`].
+async fn readiness<T: Send + Sync + Clone + 'static>(
+    State(ctx): State<AppContext<T>>,
+    request: Request,
+) -> Result<Response> {
+    if let Some(limited) = ctx.health.limit(&request) {
+        return Ok(limited);
+    }
+    if ctx.cancellation_token.is_cancelled() {
+        return format::render()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .json(Health { ok: false });
+    }
+
+    let report = ctx.health.run(&ctx).await;
+    if report.ok {
+        format::json(report)
+    } else {
+        format::render()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .json(report)
+    }
+}
+
 /// Defines and returns the health-related routes.
 pub fn routes<T: Send + Sync + Clone + 'static>() -> Routes<T> {
-    Routes::new().add("/_ping", get(ping))
+    Routes::new()
+        .add("/_ping", get(ping))
+        .add("/_readiness", get(readiness))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use async_trait::async_trait;
+    use axum_test::TestServer;
+
+    use super::*;
+    use crate::{
+        controller::health::{
+            Criticality, HealthCheck, HealthRateLimit, HealthRegistry, HealthStatus,
+            MigrationStatus, PollableHealthCheck,
+        },
+        tests_cfg,
+    };
+
+    struct FailingCheck;
+
+    #[async_trait]
+    impl HealthCheck<()> for FailingCheck {
+        fn name(&self) -> String {
+            "search".to_string()
+        }
+
+        async fn check(&self, _ctx: &AppContext<()>) -> HealthStatus {
+            HealthStatus::Error("down".to_string())
+        }
+    }
+
+    fn server(ctx: AppContext<()>) -> TestServer {
+        let mut router = axum::Router::new();
+        for handler in routes().handlers {
+            router = router.route(&handler.uri, handler.method);
+        }
+        TestServer::new(router.with_state(ctx)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_registered_checks() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default();
+        let registry = ctx.health.clone();
+        let server = server(ctx);
+
+        let res = server.get("/_readiness").await;
+        res.assert_status_ok();
+
+        registry.register_check(FailingCheck);
+        let res = server.get("/_readiness").await;
+        res.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            res.json::<serde_json::Value>()["components"]["search"]["status"],
+            "error"
+        );
+
+        assert!(registry.unregister_check("search"));
+        let res = server.get("/_readiness").await;
+        res.assert_status_ok();
+        assert!(res.json::<serde_json::Value>()["components"]["search"].is_null());
+    }
+
+    #[tokio::test]
+    async fn readiness_is_rate_limited() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default();
+        ctx.health.set_rate_limit(Some(HealthRateLimit::new(
+            1,
+            std::time::Duration::from_secs(60),
+        )));
+        let server = server(ctx);
+
+        server.get("/_readiness").await.assert_status_ok();
+        server
+            .get("/_readiness")
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn readiness_reflects_pollable_checks() {
+        let available = Arc::new(AtomicBool::new(true));
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default().with_check({
+            let available = available.clone();
+            PollableHealthCheck::new("flags", move |_ctx: AppContext<()>| {
+                let available = available.load(Ordering::SeqCst);
+                async move {
+                    if available {
+                        HealthStatus::Ok
+                    } else {
+                        HealthStatus::Error("flags service unreachable".to_string())
+                    }
+                }
+            })
+        });
+        let server = server(ctx);
+
+        let res = server.get("/_readiness").await;
+        res.assert_status_ok();
+        assert_eq!(
+            res.json::<serde_json::Value>()["components"]["flags"]["status"],
+            "ok"
+        );
+
+        available.store(false, Ordering::SeqCst);
+        let res = server.get("/_readiness").await;
+        res.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            res.json::<serde_json::Value>()["components"]["flags"]["message"],
+            "flags service unreachable"
+        );
+    }
+
+    #[tokio::test]
+    async fn non_critical_pollable_checks_keep_readiness() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default().with_check(
+            PollableHealthCheck::new("flags", |_ctx: AppContext<()>| async {
+                HealthStatus::Error("down".to_string())
+            })
+            .with_criticality(Criticality::NonCritical),
+        );
+        let server = server(ctx);
+
+        let res = server.get("/_readiness").await;
+        res.assert_status_ok();
+        assert_eq!(res.json::<serde_json::Value>()["status"], "degraded");
+    }
+
+    #[tokio::test]
+    async fn readiness_waits_for_background_migrations() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.health = HealthRegistry::default();
+        let status = MigrationStatus::new();
+        ctx.health.register_check(status.clone());
+        let server = server(ctx);
+
+        let res = server.get("/_readiness").await;
+        res.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            res.json::<serde_json::Value>()["components"]["migrations"]["message"],
+            "migrations are running"
+        );
+
+        status.complete();
+        assert!(status.is_completed());
+        server.get("/_readiness").await.assert_status_ok();
+    }
+
+    #[tokio::test]
+    async fn readiness_fails_on_failed_background_migrations() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        let status = MigrationStatus::new();
+        ctx.health = HealthRegistry::default().with_check(status.clone());
+        let server = server(ctx);
+
+        status.fail("relation already exists");
+
+        let res = server.get("/_readiness").await;
+        res.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            res.json::<serde_json::Value>()["components"]["migrations"]["message"],
+            "relation already exists"
+        );
+    }
 }
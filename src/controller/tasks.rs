@@ -0,0 +1,254 @@
+//! An HTTP runner for the registered tasks, to run maintenance tasks
+//! remotely (e.g. from an admin dashboard).
+//!
+//! The runner is opt-in, mounted behind the application authentication:
+//!
+//! ```rust,ignore
+//! fn routes(ctx: &AppContext) -> AppRoutes {
+//!     let mut tasks = Tasks::default();
+//!     App::register_tasks(&mut tasks);
+//!     AppRoutes::with_default_routes().add_route(controller::tasks::routes(Arc::new(tasks)))
+//! }
+//! ```
+//!
+//! The authentication middleware grants the caller permissions by adding
+//! [`TaskPermissions`] to the request extensions. Requests without it are
+//! rejected with `401 Unauthorized`, so a runner mounted without
+//! authentication runs nothing. A task only runs for callers having its
+//! [`Task::required_permission`], or the `tasks:<name>` permission (e.g.
+//! `tasks:db:seed`) when it declares none.
+//!
+//! [`Task::required_permission`]: crate::task::Task::required_permission
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Response,
+    routing::post,
+    Extension,
+};
+
+use super::{format, routes::Routes, ErrorDetail, Json};
+use crate::{
+    app::AppContext,
+    errors::Error,
+    task::{Tasks, Vars},
+    Result,
+};
+
+/// The permissions of the caller of the HTTP task runner, added to the
+/// request extensions by the authentication middleware.
+#[derive(Debug, Clone, Default)]
+pub struct TaskPermissions(pub Vec<String>);
+
+impl TaskPermissions {
+    /// Whether the caller has the given permission.
+    #[must_use]
+    pub fn has(&self, permission: &str) -> bool {
+        self.0.iter().any(|granted| granted == permission)
+    }
+}
+
+/// Run a task by name with the [`Vars`] given in the request body, once the
+/// caller is authenticated and allowed to.
+async fn run<T: Send + Sync + Clone + 'static>(
+    tasks: Arc<Tasks<T>>,
+    ctx: AppContext<T>,
+    name: String,
+    permissions: Option<TaskPermissions>,
+    vars: Vars,
+) -> Result<Response> {
+    let Some(permissions) = permissions else {
+        tracing::warn!(task = %name, "task run without caller permissions");
+        return Err(Error::Unauthorized(
+            "the task runner requires the caller permissions".to_string(),
+        ));
+    };
+    let permission = tasks
+        .required_permission(&name)
+        .map_err(|_| Error::NotFound)?
+        .unwrap_or_else(|| format!("tasks:{name}"));
+    if !permissions.has(&permission) {
+        tracing::warn!(task = %name, permission = %permission, "task run forbidden");
+        let description = format!("running `{name}` requires the `{permission}` permission");
+        return Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            ErrorDetail::new("forbidden", description.as_str()),
+        ));
+    }
+
+    format::json(tasks.run_detailed(&ctx, &name, &vars).await?)
+}
+
+/// Defines and returns the task runner routes: `POST /_tasks/:name`, with the
+/// task [`Vars`] as a JSON body.
+pub fn routes<T: Send + Sync + Clone + 'static>(tasks: Arc<Tasks<T>>) -> Routes<T> {
+    Routes::new().add(
+        "/_tasks/:name",
+        post(
+            move |State(ctx): State<AppContext<T>>,
+                  Path(name): Path<String>,
+                  permissions: Option<Extension<TaskPermissions>>,
+                  Json(vars): Json<Vars>| {
+                run(
+                    tasks.clone(),
+                    ctx,
+                    name,
+                    permissions.map(|Extension(permissions)| permissions),
+                    vars,
+                )
+            },
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use axum_test::TestServer;
+
+    use super::*;
+    use crate::{
+        task::{Task, TaskInfo},
+        tests_cfg,
+    };
+
+    /// A task needing the `users:purge` permission, counting its runs.
+    struct PurgeTask(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Task<()> for PurgeTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "users:purge".to_string(),
+                detail: "purges inactive users".to_string(),
+            }
+        }
+
+        fn required_permission(&self) -> Option<String> {
+            Some("users:purge".to_string())
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// A task declaring no permission, counting its runs.
+    struct ClearCacheTask(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Task<()> for ClearCacheTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "cache:clear".to_string(),
+                detail: "clears the cache".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Serves the runner, granting the caller `permissions` when given.
+    async fn serve(permissions: Option<&[&str]>) -> (TestServer, Arc<AtomicUsize>) {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Tasks::default();
+        tasks.register(PurgeTask(runs.clone()));
+        tasks.register(ClearCacheTask(runs.clone()));
+
+        let mut router = axum::Router::new();
+        for handler in routes(Arc::new(tasks)).handlers {
+            router = router.route(&handler.uri, handler.method);
+        }
+        if let Some(permissions) = permissions {
+            let permissions =
+                TaskPermissions(permissions.iter().map(ToString::to_string).collect());
+            router = router.layer(Extension(permissions));
+        }
+        (TestServer::new(router.with_state(ctx)).unwrap(), runs)
+    }
+
+    async fn server(permissions: &[&str]) -> (TestServer, Arc<AtomicUsize>) {
+        serve(Some(permissions)).await
+    }
+
+    #[tokio::test]
+    async fn caller_without_permissions_is_unauthorized() {
+        let (server, runs) = serve(None).await;
+
+        for name in ["users:purge", "missing"] {
+            let res = server
+                .post(&format!("/_tasks/{name}"))
+                .json(&serde_json::json!({ "cli": {} }))
+                .await;
+            res.assert_status(StatusCode::UNAUTHORIZED);
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn caller_lacking_permission_is_forbidden() {
+        let (server, runs) = server(&["users:read"]).await;
+
+        let res = server
+            .post("/_tasks/users:purge")
+            .json(&serde_json::json!({ "cli": {} }))
+            .await;
+
+        res.assert_status(StatusCode::FORBIDDEN);
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn caller_with_permission_runs_task() {
+        let (server, runs) = server(&["users:purge"]).await;
+
+        let res = server
+            .post("/_tasks/users:purge")
+            .json(&serde_json::json!({ "cli": {} }))
+            .await;
+
+        res.assert_status_ok();
+        assert_eq!(res.json::<serde_json::Value>()["name"], "users:purge");
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn task_without_permission_requires_the_task_permission() {
+        let (unauthorized, runs) = server(&["users:purge"]).await;
+        let res = unauthorized
+            .post("/_tasks/cache:clear")
+            .json(&serde_json::json!({ "cli": {} }))
+            .await;
+        res.assert_status(StatusCode::FORBIDDEN);
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+        let (authorized, runs) = server(&["tasks:cache:clear"]).await;
+        let res = authorized
+            .post("/_tasks/cache:clear")
+            .json(&serde_json::json!({ "cli": {} }))
+            .await;
+        res.assert_status_ok();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn unknown_task_is_not_found() {
+        let (server, _) = server(&[]).await;
+
+        let res = server
+            .post("/_tasks/missing")
+            .json(&serde_json::json!({ "cli": {} }))
+            .await;
+
+        res.assert_status(StatusCode::NOT_FOUND);
+    }
+}
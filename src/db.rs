@@ -19,7 +19,9 @@ use tracing::info;
 use super::Result as AppResult;
 use crate::{
     app::{AppContext, Hooks},
-    config, doctor,
+    config,
+    controller::health::MigrationStatus,
+    doctor,
     errors::Error,
 };
 
@@ -182,6 +184,45 @@ pub async fn migrate<M: MigratorTrait>(db: &DatabaseConnection) -> Result<(), se
     M::up(db, None).await
 }
 
+/// Apply migrations in the background, without blocking the application boot.
+///
+/// The returned [`MigrationStatus`] is registered in the application health
+/// checks, so readiness fails until the migrations complete.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// async fn after_context(ctx: AppContext) -> Result<AppContext> {
+///     db::migrate_in_background::<_, Migrator>(&ctx);
+///     Ok(ctx)
+/// }
+/// ```
+pub fn migrate_in_background<T, M>(ctx: &AppContext<T>) -> MigrationStatus
+where
+    T: Send + Sync + Clone + 'static,
+    M: MigratorTrait,
+{
+    let status = MigrationStatus::new();
+    ctx.health.register_check(status.clone());
+
+    let db = ctx.db.clone();
+    let signal = status.clone();
+    tokio::spawn(async move {
+        info!("running migrations in the background");
+        match migrate::<M>(&db).await {
+            Ok(()) => {
+                info!("background migrations completed");
+                signal.complete();
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "background migrations failed");
+                signal.fail(&err.to_string());
+            }
+        }
+    });
+    status
+}
+
 /// Check the migration status of the database.
 ///
 /// # Errors
@@ -1,9 +1,14 @@
 //! initialization application logger.
+use std::sync::OnceLock;
+
 use serde::{Deserialize, Serialize};
 use serde_variant::to_variant_name;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{prelude::*, reload, EnvFilter, Registry};
+
+use crate::{app::Hooks, config, Error, Result};
 
-use crate::{app::Hooks, config};
+/// The handle swapping the filter of the installed logger, see [`reload`].
+static FILTER_RELOAD: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 // Define an enumeration for log levels
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
@@ -70,35 +75,159 @@ pub fn init<H: Hooks>(config: &config::Logger) {
         return;
     }
 
-    let filter = EnvFilter::try_from_default_env()
-        .or_else(|_| {
-            // user wanted a specific filter, don't care about our internal whitelist
-            // or, if no override give them the default whitelisted filter (most common)
-            config.override_filter.as_ref().map_or_else(
-                || {
-                    EnvFilter::try_new(
-                        MODULE_WHITELIST
-                            .iter()
-                            .map(|m| format!("{}={}", m, config.level))
-                            .chain(std::iter::once(format!(
-                                "{}={}",
-                                H::app_name(),
-                                config.level
-                            )))
-                            .collect::<Vec<_>>()
-                            .join(","),
-                    )
-                },
-                EnvFilter::try_new,
+    let filter = env_filter(config, H::app_name()).expect("logger initialization failed");
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = FILTER_RELOAD.set(handle);
+
+    let layer = match config.format {
+        Format::Compact => tracing_subscriber::fmt::layer().compact().boxed(),
+        Format::Pretty => tracing_subscriber::fmt::layer().pretty().boxed(),
+        Format::Json => tracing_subscriber::fmt::layer().json().boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layer)
+        .init();
+
+    if config.log_panics {
+        install_panic_hook();
+    }
+}
+
+/// Apply the filtering rules of a changed logger config (e.g. a new `level`)
+/// to the installed logger, without restarting the application. `RUST_LOG`
+/// still takes precedence. The log format cannot be changed at runtime.
+///
+/// # Errors
+///
+/// When the logger was not initialized with [`init`], or the filter is
+/// invalid.
+pub fn reload<H: Hooks>(config: &config::Logger) -> Result<()> {
+    let handle = FILTER_RELOAD
+        .get()
+        .ok_or_else(|| Error::Message("the logger is not initialized".to_string()))?;
+    let filter = env_filter(config, H::app_name())
+        .map_err(|err| Error::Message(format!("invalid log filter: {err}")))?;
+    reload_filter(handle, filter)?;
+    tracing::info!(level = %config.level, "logger filter reloaded");
+    Ok(())
+}
+
+fn reload_filter(handle: &reload::Handle<EnvFilter, Registry>, filter: EnvFilter) -> Result<()> {
+    handle
+        .reload(filter)
+        .map_err(|err| Error::Message(format!("could not reload the log filter: {err}")))
+}
+
+/// Build the logger filter, following the rules described in [`init`].
+fn env_filter(
+    config: &config::Logger,
+    app_name: &str,
+) -> std::result::Result<EnvFilter, tracing_subscriber::filter::ParseError> {
+    EnvFilter::try_from_default_env().or_else(|_| config_filter(config, app_name))
+}
+
+fn config_filter(
+    config: &config::Logger,
+    app_name: &str,
+) -> std::result::Result<EnvFilter, tracing_subscriber::filter::ParseError> {
+    // user wanted a specific filter, don't care about our internal whitelist
+    // or, if no override give them the default whitelisted filter (most common)
+    config.override_filter.as_ref().map_or_else(
+        || {
+            EnvFilter::try_new(
+                MODULE_WHITELIST
+                    .iter()
+                    .map(|m| format!("{}={}", m, config.level))
+                    .chain(std::iter::once(format!("{}={}", app_name, config.level)))
+                    .collect::<Vec<_>>()
+                    .join(","),
             )
-        })
-        .expect("logger initialization failed");
+        },
+        EnvFilter::try_new,
+    )
+}
 
-    let builder = tracing_subscriber::FmtSubscriber::builder().with_env_filter(filter);
+/// Wrap the current panic hook with one that logs the panic payload and
+/// location with `tracing`, so panics land in the same log pipeline as
+/// everything else. The previous hook still runs after logging.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let msg = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("no error details");
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
 
-    match config.format {
-        Format::Compact => builder.compact().init(),
-        Format::Pretty => builder.pretty().init(),
-        Format::Json => builder.json().init(),
-    };
+        tracing::error!(err.msg = msg, err.location = location, "panic");
+        previous(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_cfg::capture::Capture;
+
+    #[test]
+    fn reload_updates_the_log_level() {
+        let mut config = config::Logger {
+            enable: true,
+            level: LogLevel::Info,
+            ..crate::tests_cfg::config::test_config().logger
+        };
+        let (filter, handle) = reload::Layer::new(config_filter(&config, "app").unwrap());
+        let capture = Capture::default();
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::registry()
+                .with(filter)
+                .with(capture.clone()),
+        );
+
+        tracing::debug!("before reload");
+        config.level = LogLevel::Debug;
+        reload_filter(&handle, config_filter(&config, "app").unwrap()).unwrap();
+        tracing::debug!("after reload");
+
+        assert!(capture.events_with_message("before reload").is_empty());
+        assert_eq!(capture.events_with_message("after reload").len(), 1);
+    }
+
+    /// Held by the tests replacing the process-wide panic hook.
+    static PANIC_HOOK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn can_log_panics() {
+        let _lock = PANIC_HOOK
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+        let chained = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let original = std::panic::take_hook();
+        let called = chained.clone();
+        std::panic::set_hook(Box::new(move |_| {
+            called.store(true, std::sync::atomic::Ordering::SeqCst);
+        }));
+        install_panic_hook();
+        let result = std::panic::catch_unwind(|| panic!("boom"));
+        std::panic::set_hook(original);
+
+        assert!(result.is_err());
+        assert!(chained.load(std::sync::atomic::Ordering::SeqCst));
+        let events = capture.events_with_message("panic");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, tracing::Level::ERROR);
+        assert_eq!(events[0].field("err.msg"), Some("boom"));
+        assert!(events[0]
+            .field("err.location")
+            .is_some_and(|l| l.starts_with("src/logger.rs:")));
+    }
 }
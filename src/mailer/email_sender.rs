@@ -43,12 +43,19 @@ impl EmailSender {
     /// when could not initialize SMTP transport
     pub fn smtp(config: &config::SmtpMailer) -> Result<Self> {
         let mut email_builder = if config.secure {
-            lettre::AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
-                .map_err(|error| {
-                    tracing::error!(err.msg = %error, err.detail = ?error, "smtp_init_error");
-                    Error::Any("error initialize smtp mailer".to_string().into())
-                })?
-                .port(config.port)
+            match config.tls {
+                config::SmtpTls::StartTls => {
+                    lettre::AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                }
+                config::SmtpTls::Tls => {
+                    lettre::AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                }
+            }
+            .map_err(|error| {
+                tracing::error!(err.msg = %error, err.detail = ?error, "smtp_init_error");
+                Error::Any("error initialize smtp mailer".to_string().into())
+            })?
+            .port(config.port)
         } else {
             lettre::AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
                 .port(config.port)
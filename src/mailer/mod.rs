@@ -5,14 +5,18 @@
 mod email_sender;
 mod template;
 
+use std::path::PathBuf;
+
 use async_trait::async_trait;
 pub use email_sender::EmailSender;
 use include_dir::Dir;
 use serde::{Deserialize, Serialize};
 use sidekiq::Worker;
 
+pub use self::template::Content;
 use self::template::Template;
 use super::{app::AppContext, worker::AppWorker, Result};
+use crate::errors::Error;
 
 pub const DEFAULT_FROM_SENDER: &str = "System <system@example.com>";
 
@@ -28,6 +32,40 @@ pub struct Args {
     pub cc: Option<String>,
 }
 
+impl Args {
+    /// Sets the template locals from a typed struct, which is serialized into
+    /// the template context.
+    ///
+    /// # Errors
+    ///
+    /// When the given locals could not be serialized
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loco_rs::mailer::Args;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Welcome {
+    ///     name: String,
+    /// }
+    ///
+    /// let args = Args {
+    ///     to: "user@example.com".to_string(),
+    ///     ..Default::default()
+    /// }
+    /// .with_locals(&Welcome { name: "loco".to_string() })
+    /// .unwrap();
+    ///
+    /// assert_eq!(args.locals["name"], "loco");
+    /// ```
+    pub fn with_locals<L: Serialize>(mut self, locals: &L) -> Result<Self> {
+        self.locals = serde_json::to_value(locals)?;
+        Ok(self)
+    }
+}
+
 /// The structure representing an email details.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Email {
@@ -59,6 +97,13 @@ pub struct MailerOpts {
 
 /// The `Mailer` trait defines methods for sending emails and processing email
 /// templates.
+///
+/// Emails can be delivered in two ways:
+/// * `mail` / `mail_template` hand the email to the [`MailerWorker`], which
+///   delivers it according to the configured worker mode. When delivered
+///   through the queue, failed deliveries are retried.
+/// * `deliver` / `deliver_template` send the email immediately, returning the
+///   transport error to the caller.
 #[async_trait]
 pub trait Mailer<T: Send + Sync + Clone + 'static> {
     /// Returns default options for the mailer.
@@ -70,15 +115,43 @@ pub trait Mailer<T: Send + Sync + Clone + 'static> {
         }
     }
 
-    /// Sends an email using the provided [`AppContext`] and email details.
-    async fn mail(ctx: &AppContext<T>, email: &Email) -> Result<()> {
+    /// The folder holding this mailer's templates on disk, used by
+    /// [`Mailer::mail_template_from`]. Each template is a sub folder containing
+    /// `subject.t`, `text.t` and `html.t`.
+    ///
+    /// Not set by default: templates are embedded in the binary with
+    /// `include_dir!` and passed to [`Mailer::mail_template`]. Override it to
+    /// load the templates at runtime instead, e.g. from a folder read from the
+    /// app config.
+    #[must_use]
+    fn templates_root() -> Option<PathBuf> {
+        None
+    }
+
+    /// Applies the mailer default options on the given email.
+    #[must_use]
+    fn prepare(email: &Email) -> Email {
         let opts = Self::opts();
         let mut email = email.clone();
 
         email.from = Some(email.from.unwrap_or_else(|| opts.from.clone()));
         email.reply_to = email.reply_to.or_else(|| opts.reply_to.clone());
+        email
+    }
+
+    /// Renders the email from the embedded template directory and arguments.
+    ///
+    /// # Errors
+    ///
+    /// When one of the template files is missing or fails to render
+    fn render_template(dir: &Dir<'_>, args: &Args) -> Result<Email> {
+        let content = Template::new(dir).render(&args.locals)?;
+        Ok(Self::prepare(&args_to_email(args, content)))
+    }
 
-        MailerWorker::perform_later(ctx, email.clone())
+    /// Sends an email using the provided [`AppContext`] and email details.
+    async fn mail(ctx: &AppContext<T>, email: &Email) -> Result<()> {
+        MailerWorker::perform_later(ctx, Self::prepare(email))
             .await
             .map_err(Box::from)?;
         Ok(())
@@ -87,21 +160,63 @@ pub trait Mailer<T: Send + Sync + Clone + 'static> {
     /// Renders and sends an email using the provided [`AppContext`], template
     /// directory, and arguments.
     async fn mail_template(ctx: &AppContext<T>, dir: &Dir<'_>, args: Args) -> Result<()> {
-        let content = Template::new(dir).render(&args.locals)?;
-        Self::mail(
-            ctx,
-            &Email {
-                from: args.from.clone(),
-                to: args.to.clone(),
-                reply_to: args.reply_to.clone(),
-                subject: content.subject,
-                text: content.text,
-                html: content.html,
-                bcc: args.bcc.clone(),
-                cc: args.cc.clone(),
-            },
-        )
-        .await
+        let email = Self::render_template(dir, &args)?;
+        Self::mail(ctx, &email).await
+    }
+
+    /// Renders and sends an email from the template named `name` under
+    /// [`Mailer::templates_root`].
+    ///
+    /// # Errors
+    ///
+    /// When the mailer has no templates root, or the template fails to render
+    async fn mail_template_from(ctx: &AppContext<T>, name: &str, args: Args) -> Result<()> {
+        let root = Self::templates_root().ok_or_else(|| {
+            Error::Message(
+                "mailer has no templates root, override `Mailer::templates_root` or use \
+                 `Mailer::mail_template`"
+                    .to_string(),
+            )
+        })?;
+        let content = Template::from_folder(root.join(name)).render(&args.locals)?;
+        Self::mail(ctx, &args_to_email(&args, content)).await
+    }
+
+    /// Sends an email immediately with the configured [`EmailSender`],
+    /// bypassing the worker.
+    ///
+    /// # Errors
+    ///
+    /// When no email sender is configured or the delivery fails
+    async fn deliver(ctx: &AppContext<T>, email: &Email) -> Result<()> {
+        let sender = ctx.mailer.as_ref().ok_or_else(|| {
+            Error::Message("attempting to send email but no email sender configured".to_string())
+        })?;
+        sender.mail(&Self::prepare(email)).await
+    }
+
+    /// Renders and sends an email immediately, bypassing the worker.
+    ///
+    /// # Errors
+    ///
+    /// When the template fails to render or the delivery fails
+    async fn deliver_template(ctx: &AppContext<T>, dir: &Dir<'_>, args: Args) -> Result<()> {
+        let email = Self::render_template(dir, &args)?;
+        Self::deliver(ctx, &email).await
+    }
+}
+
+/// Builds an [`Email`] from the given arguments and rendered content.
+fn args_to_email(args: &Args, content: Content) -> Email {
+    Email {
+        from: args.from.clone(),
+        to: args.to.clone(),
+        reply_to: args.reply_to.clone(),
+        subject: content.subject,
+        text: content.text,
+        html: content.html,
+        bcc: args.bcc.clone(),
+        cc: args.cc.clone(),
     }
 }
 
@@ -123,8 +238,9 @@ impl<T: Send + Sync + Clone + 'static> AppWorker<Email, T> for MailerWorker<T> {
 #[async_trait]
 impl<T: Send + Sync + Clone> Worker<Email> for MailerWorker<T> {
     /// Returns options for the mailer worker, specifying the queue to process.
+    /// Failed deliveries are retried by the queue.
     fn opts() -> sidekiq::WorkerOpts<Email, Self> {
-        sidekiq::WorkerOpts::new().queue("mailer")
+        sidekiq::WorkerOpts::new().queue("mailer").retry(true)
     }
 
     /// Performs the email sending operation using the provided [`AppContext`]
@@ -139,3 +255,113 @@ impl<T: Send + Sync + Clone> Worker<Email> for MailerWorker<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use include_dir::include_dir;
+    use serde::Serialize;
+
+    use super::*;
+    use crate::tests_cfg;
+
+    static WELCOME: Dir<'_> = include_dir!("tests/fixtures/email_template/welcome");
+
+    #[derive(Serialize)]
+    struct WelcomeArgs {
+        name: String,
+        token: String,
+    }
+
+    struct WelcomeMailer;
+    impl Mailer<()> for WelcomeMailer {
+        fn opts() -> MailerOpts {
+            MailerOpts {
+                from: "welcome@loco.rs".to_string(),
+                reply_to: Some("support@loco.rs".to_string()),
+            }
+        }
+
+        fn templates_root() -> Option<PathBuf> {
+            Some(PathBuf::from("tests/fixtures/email_template"))
+        }
+    }
+
+    impl WelcomeMailer {
+        fn args() -> Args {
+            Args {
+                to: "user1@framework.com".to_string(),
+                ..Default::default()
+            }
+            .with_locals(&WelcomeArgs {
+                name: "loco".to_string(),
+                token: "1111-2222".to_string(),
+            })
+            .unwrap()
+        }
+    }
+
+    #[test]
+    fn can_render_welcome_mailer() {
+        let email = WelcomeMailer::render_template(&WELCOME, &WelcomeMailer::args()).unwrap();
+
+        assert_eq!(email.subject, "Welcome loco");
+        assert!(email.text.contains("/verify/1111-2222"));
+        assert!(email.html.contains("/verify/1111-2222"));
+        assert_eq!(email.from, Some("welcome@loco.rs".to_string()));
+        assert_eq!(email.reply_to, Some("support@loco.rs".to_string()));
+        assert_eq!(email.to, "user1@framework.com");
+    }
+
+    #[tokio::test]
+    async fn can_deliver_welcome_mailer() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.mailer = Some(EmailSender::stub());
+
+        WelcomeMailer::deliver_template(&ctx, &WELCOME, WelcomeMailer::args())
+            .await
+            .unwrap();
+
+        let deliveries = ctx.mailer.unwrap().deliveries();
+        assert_eq!(deliveries.count, 1);
+        assert!(deliveries.messages[0].contains("Subject: Welcome loco"));
+    }
+
+    #[tokio::test]
+    async fn can_mail_welcome_mailer_through_worker() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.mailer = Some(EmailSender::stub());
+
+        WelcomeMailer::mail_template_from(&ctx, "welcome", WelcomeMailer::args())
+            .await
+            .unwrap();
+
+        assert_eq!(ctx.mailer.unwrap().deliveries().count, 1);
+    }
+
+    #[tokio::test]
+    async fn mail_template_from_requires_a_templates_root() {
+        struct EmbeddedMailer;
+        impl Mailer<()> for EmbeddedMailer {}
+
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.mailer = Some(EmailSender::stub());
+
+        let err = EmbeddedMailer::mail_template_from(&ctx, "welcome", WelcomeMailer::args())
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().starts_with("mailer has no templates root"));
+        assert_eq!(ctx.mailer.unwrap().deliveries().count, 0);
+    }
+
+    #[tokio::test]
+    async fn deliver_without_sender_returns_error() {
+        let ctx = tests_cfg::app::get_app_context().await;
+
+        assert!(
+            WelcomeMailer::deliver_template(&ctx, &WELCOME, WelcomeMailer::args())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -14,6 +14,8 @@
 //! let content = Template::new("contnt").render(&args);
 //! ```
 
+use std::path::PathBuf;
+
 use include_dir::Dir;
 
 use crate::{errors::Error, tera, Result};
@@ -36,6 +38,14 @@ fn embedded_file(dir: &Dir<'_>, name: &str) -> Result<String> {
     .to_string())
 }
 
+/// Reads a template file from the provided folder on disk and returns its
+/// content as a string.
+fn folder_file(path: &std::path::Path, name: &str) -> Result<String> {
+    let file = path.join(name);
+    std::fs::read_to_string(&file)
+        .map_err(|_| Error::Message(format!("no mailer template file found {}", file.display())))
+}
+
 /// A structure representing the content of an email, including subject, text,
 /// and HTML.
 #[derive(Clone, Debug)]
@@ -45,25 +55,51 @@ pub struct Content {
     pub html: String,
 }
 
+/// The location of the template files.
+#[derive(Debug, Clone)]
+enum Source<'a> {
+    /// Files embedded in the binary with `include_dir!`
+    Embedded(&'a Dir<'a>),
+    /// Files read from a folder on disk at render time
+    Folder(PathBuf),
+}
+
 /// A structure for managing template rendering using Tera.
 #[derive(Debug, Clone)]
 pub struct Template<'a> {
-    /// The directory containing the embedded template files.
-    dir: &'a Dir<'a>,
+    /// The location of the template files.
+    source: Source<'a>,
 }
 
 impl<'a> Template<'a> {
     /// Creates a new `Template` instance with the provided directory.
     pub const fn new(dir: &'a Dir<'_>) -> Self {
-        Self { dir }
+        Self {
+            source: Source::Embedded(dir),
+        }
+    }
+
+    /// Creates a new `Template` instance reading the template files from the
+    /// given folder on disk.
+    pub fn from_folder<P: Into<PathBuf>>(path: P) -> Template<'static> {
+        Template {
+            source: Source::Folder(path.into()),
+        }
+    }
+
+    fn file(&self, name: &str) -> Result<String> {
+        match &self.source {
+            Source::Embedded(dir) => embedded_file(dir, name),
+            Source::Folder(path) => folder_file(path, name),
+        }
     }
 
     /// Renders the email content based on the provided locals using the
-    /// embedded templates.
+    /// template files.
     pub fn render(&self, locals: &serde_json::Value) -> Result<Content> {
-        let subject_t = embedded_file(self.dir, SUBJECT)?;
-        let text_t = embedded_file(self.dir, TEXT)?;
-        let html_t = embedded_file(self.dir, HTML)?;
+        let subject_t = self.file(SUBJECT)?;
+        let text_t = self.file(TEXT)?;
+        let html_t = self.file(HTML)?;
 
         // TODO(consider): check+consider offloading to tokio async this work
         let text = tera::render_string(&text_t, locals)?;
@@ -95,4 +131,32 @@ mod tests {
             Template::new(&include_dir!("tests/fixtures/email_template/test")).render(&args)
         );
     }
+
+    #[test]
+    fn can_render_template_from_folder() {
+        let args = serde_json::json!({
+            "verifyToken": "1111-2222-3333-4444",
+            "name": "Can render test template",
+        });
+        let embedded = Template::new(&include_dir!("tests/fixtures/email_template/test"))
+            .render(&args)
+            .unwrap();
+        let folder = Template::from_folder("tests/fixtures/email_template/test")
+            .render(&args)
+            .unwrap();
+
+        assert_eq!(embedded.subject, folder.subject);
+        assert_eq!(embedded.text, folder.text);
+        assert_eq!(embedded.html, folder.html);
+    }
+
+    #[test]
+    fn missing_folder_template_returns_error() {
+        let args = serde_json::json!({});
+        assert!(
+            Template::from_folder("tests/fixtures/email_template/not-exists")
+                .render(&args)
+                .is_err()
+        );
+    }
 }
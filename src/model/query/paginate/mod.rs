@@ -1,6 +1,10 @@
-use sea_orm::{prelude::*, Condition, DatabaseConnection, EntityTrait, QueryFilter, SelectorTrait};
+use sea_orm::{
+    prelude::*, Condition, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect, SelectorTrait,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::controller::pagination::Pagination;
+
 /// Set the default pagination page size.
 const fn default_page_size() -> u64 {
     25
@@ -64,6 +68,32 @@ impl Default for PaginationQuery {
     }
 }
 
+impl From<&Pagination> for PaginationQuery {
+    fn from(pagination: &Pagination) -> Self {
+        Self {
+            page_size: pagination.per_page,
+            page: pagination.page,
+        }
+    }
+}
+
+/// Select the page of a query requested with the [`Pagination`] extractor.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let items = Entity::find().page(&pagination).all(&ctx.db).await?;
+/// ```
+pub trait PaginateExt: QuerySelect {
+    /// Select the requested page, see [`Pagination::apply`].
+    #[must_use]
+    fn page(self, pagination: &Pagination) -> Self {
+        pagination.apply(self)
+    }
+}
+
+impl<Q: QuerySelect> PaginateExt for Q {}
+
 /// Deserialize pagination filter from string to u64 following a bug in
 /// `serde_urlencoded`.
 fn deserialize_pagination_filter<'de, D>(deserializer: D) -> Result<u64, D::Error>
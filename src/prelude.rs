@@ -21,9 +21,11 @@ pub use crate::model::{query, Authenticable, ModelError, ModelResult};
 pub use crate::{
     app::{AppContext, Initializer},
     controller::{
-        format,
+        format::{self, ResponseExt},
         middleware::format::{Format, RespondTo},
-        not_found, unauthorized,
+        not_found,
+        pagination::Pagination,
+        unauthorized,
         views::{engines::TeraView, ViewEngine, ViewRenderer},
         Json, Routes,
     },
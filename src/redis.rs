@@ -26,7 +26,6 @@ pub async fn converge(
     Ok(())
 }
 
-#[cfg(feature = "with-db")]
 /// Run Redis ping command
 pub async fn ping(pool: &Pool<RedisConnectionManager>) -> Result<()> {
     let mut conn = pool.get().await?;
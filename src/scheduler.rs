@@ -0,0 +1,341 @@
+//! # Scheduler
+//!
+//! Fires registered tasks periodically, in the application process.
+//!
+//! A task that is not [`Task::concurrency_safe`] is never fired while a
+//! previous run is still in flight: the overlapping fire is skipped. Tasks
+//! declaring themselves concurrency safe may overlap.
+//!
+//! ```rust,ignore
+//! let scheduler = Scheduler::new(tasks)
+//!     .every(Duration::from_secs(60), "cleanup", Vars::default())?;
+//! tokio::spawn(async move { scheduler.run(&ctx).await });
+//! ```
+//!
+//! [`Task::concurrency_safe`]: crate::task::Task::concurrency_safe
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use futures_util::future::join_all;
+use tokio::{
+    task::JoinHandle,
+    time::{interval_at, Instant, MissedTickBehavior},
+};
+
+use crate::{
+    app::AppContext,
+    errors::Error,
+    task::{Tasks, Vars},
+    Result,
+};
+
+/// A task fired periodically.
+#[derive(Debug, Clone)]
+pub struct ScheduledJob {
+    pub task: String,
+    pub vars: Vars,
+    pub every: Duration,
+}
+
+/// Fires tasks periodically, see the [module docs](self).
+pub struct Scheduler<T: Send + Sync + Clone> {
+    tasks: Arc<Tasks<T>>,
+    jobs: Vec<ScheduledJob>,
+    /// The number of in-flight runs by task name
+    running: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl<T: Send + Sync + Clone + 'static> Scheduler<T> {
+    #[must_use]
+    pub fn new(tasks: Tasks<T>) -> Self {
+        Self {
+            tasks: Arc::new(tasks),
+            jobs: vec![],
+            running: Arc::default(),
+        }
+    }
+
+    /// Fire `task` every `every`, the first time after one period.
+    ///
+    /// # Errors
+    ///
+    /// When `every` is zero.
+    pub fn every(mut self, every: Duration, task: &str, vars: Vars) -> Result<Self> {
+        if every.is_zero() {
+            return Err(Error::Message(format!(
+                "task `{task}` cannot be scheduled every 0s"
+            )));
+        }
+        self.jobs.push(ScheduledJob {
+            task: task.to_string(),
+            vars,
+            every,
+        });
+        Ok(self)
+    }
+
+    /// The scheduled jobs.
+    #[must_use]
+    pub fn jobs(&self) -> &[ScheduledJob] {
+        &self.jobs
+    }
+
+    /// Fire a task in the background. Returns `None` when the fire is
+    /// skipped, because the task is not concurrency safe and a previous run
+    /// is still in flight.
+    ///
+    /// # Errors
+    ///
+    /// When the task is not found.
+    pub fn fire(
+        &self,
+        ctx: &AppContext<T>,
+        task: &str,
+        vars: &Vars,
+    ) -> Result<Option<JoinHandle<Result<()>>>> {
+        let concurrency_safe = self
+            .tasks
+            .is_concurrency_safe(task)
+            .ok_or_else(|| Error::TaskNotFound(task.to_string()))?;
+
+        let Some(in_flight) = InFlight::enter(&self.running, task, concurrency_safe) else {
+            tracing::info!(
+                task,
+                "skipping overlapping run of a task not concurrency safe"
+            );
+            return Ok(None);
+        };
+
+        let tasks = self.tasks.clone();
+        let ctx = ctx.clone();
+        let vars = vars.clone();
+        Ok(Some(tokio::spawn(async move {
+            // `in_flight` is dropped when the run ends, even by panicking
+            tasks.run(&ctx, &in_flight.task, &vars).await
+        })))
+    }
+
+    /// Fire the scheduled jobs until the application shuts down.
+    pub async fn run(&self, ctx: &AppContext<T>) {
+        join_all(self.jobs.iter().map(|job| async move {
+            let mut interval = interval_at(Instant::now() + job.every, job.every);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                tokio::select! {
+                    () = ctx.cancellation_token.cancelled() => break,
+                    _ = interval.tick() => {
+                        if let Err(err) = self.fire(ctx, &job.task, &job.vars) {
+                            tracing::error!(task = job.task, error = %err, "could not fire scheduled task");
+                        }
+                    }
+                }
+            }
+        }))
+        .await;
+    }
+}
+
+/// An in-flight run of a task, counted in the scheduler running tasks until
+/// dropped.
+struct InFlight {
+    running: Arc<Mutex<HashMap<String, usize>>>,
+    task: String,
+}
+
+impl InFlight {
+    /// Count a new run of `task`, unless a run is in flight and the task is
+    /// not concurrency safe.
+    fn enter(
+        running: &Arc<Mutex<HashMap<String, usize>>>,
+        task: &str,
+        concurrency_safe: bool,
+    ) -> Option<Self> {
+        let mut counts = running.lock().unwrap_or_else(PoisonError::into_inner);
+        let in_flight = counts.entry(task.to_string()).or_default();
+        if *in_flight > 0 && !concurrency_safe {
+            return None;
+        }
+        *in_flight += 1;
+        drop(counts);
+        Some(Self {
+            running: running.clone(),
+            task: task.to_string(),
+        })
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut counts = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(in_flight) = counts.get_mut(&self.task) {
+            *in_flight = in_flight.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::{
+        task::{Task, TaskInfo},
+        tests_cfg,
+    };
+
+    /// A task blocking until its gate opens.
+    struct GatedTask {
+        name: &'static str,
+        concurrency_safe: bool,
+        gate: Arc<Semaphore>,
+    }
+
+    #[async_trait]
+    impl Task<()> for GatedTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: self.name.to_string(),
+                detail: "waits for its gate".to_string(),
+            }
+        }
+
+        fn concurrency_safe(&self) -> bool {
+            self.concurrency_safe
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            self.gate.acquire().await.unwrap().forget();
+            Ok(())
+        }
+    }
+
+    /// A task panicking on every run.
+    struct PanickingTask;
+
+    #[async_trait]
+    impl Task<()> for PanickingTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "panicking".to_string(),
+                detail: "panics".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            panic!("task panicked");
+        }
+    }
+
+    fn scheduler(gate: &Arc<Semaphore>) -> Scheduler<()> {
+        let mut tasks = Tasks::default();
+        tasks.register(GatedTask {
+            name: "unsafe",
+            concurrency_safe: false,
+            gate: gate.clone(),
+        });
+        tasks.register(GatedTask {
+            name: "safe",
+            concurrency_safe: true,
+            gate: gate.clone(),
+        });
+        tasks.register(PanickingTask);
+        Scheduler::new(tasks)
+    }
+
+    #[tokio::test]
+    async fn skips_overlapping_runs_of_unsafe_tasks() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let gate = Arc::new(Semaphore::new(0));
+        let scheduler = scheduler(&gate);
+
+        let first = scheduler.fire(&ctx, "unsafe", &Vars::default()).unwrap();
+        assert!(first.is_some());
+        assert!(scheduler
+            .fire(&ctx, "unsafe", &Vars::default())
+            .unwrap()
+            .is_none());
+
+        gate.add_permits(1);
+        first.unwrap().await.unwrap().unwrap();
+
+        let again = scheduler.fire(&ctx, "unsafe", &Vars::default()).unwrap();
+        assert!(again.is_some());
+        gate.add_permits(1);
+        again.unwrap().await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn allows_overlapping_runs_of_safe_tasks() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let gate = Arc::new(Semaphore::new(0));
+        let scheduler = scheduler(&gate);
+
+        let first = scheduler.fire(&ctx, "safe", &Vars::default()).unwrap();
+        let second = scheduler.fire(&ctx, "safe", &Vars::default()).unwrap();
+        assert!(first.is_some());
+        assert!(second.is_some());
+
+        gate.add_permits(2);
+        first.unwrap().await.unwrap().unwrap();
+        second.unwrap().await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn panicking_run_is_no_longer_in_flight() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let scheduler = scheduler(&Arc::new(Semaphore::new(0)));
+
+        let first = scheduler.fire(&ctx, "panicking", &Vars::default()).unwrap();
+        assert!(first.unwrap().await.unwrap_err().is_panic());
+
+        assert_eq!(scheduler.running.lock().unwrap()["panicking"], 0);
+        let again = scheduler.fire(&ctx, "panicking", &Vars::default()).unwrap();
+        assert!(again.is_some());
+    }
+
+    #[tokio::test]
+    async fn fire_fails_on_unknown_tasks() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let scheduler = scheduler(&Arc::new(Semaphore::new(0)));
+
+        assert!(scheduler.fire(&ctx, "missing", &Vars::default()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_zero_period() {
+        let gate = Arc::new(Semaphore::new(0));
+
+        assert!(matches!(
+            scheduler(&gate).every(Duration::ZERO, "unsafe", Vars::default()),
+            Err(Error::Message(msg)) if msg == "task `unsafe` cannot be scheduled every 0s"
+        ));
+    }
+
+    #[tokio::test]
+    async fn can_run_scheduled_jobs_until_shutdown() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let gate = Arc::new(Semaphore::new(0));
+        let scheduler = Arc::new(
+            scheduler(&gate)
+                .every(Duration::from_millis(10), "unsafe", Vars::default())
+                .unwrap(),
+        );
+
+        let token = ctx.cancellation_token.clone();
+        let run = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.run(&ctx).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        token.cancel();
+        run.await.unwrap();
+
+        // the first run blocks on its gate, the later fires were skipped
+        assert_eq!(scheduler.running.lock().unwrap()["unsafe"], 1);
+        gate.add_permits(1);
+    }
+}
@@ -0,0 +1,802 @@
+//! # Server Listeners
+//!
+//! This module binds the listener configured in [`config::Listener`] and
+//! serves the connections it accepts, applying the connection settings
+//! configured in [`config::Connection`].
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+#[cfg(feature = "tls")]
+use std::{sync::RwLock, time::SystemTime};
+
+use axum::{extract::ConnectInfo, Router};
+use hyper::{body::Incoming, service::service_fn, Request};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::conn::auto,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+#[cfg(feature = "tls")]
+use tokio_rustls::{
+    rustls::{
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        ServerConfig,
+    },
+    TlsAcceptor,
+};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tower::Service;
+use tracing::{debug, info, warn};
+
+use crate::{config, errors::Error, Result};
+
+/// A bound listener, ready to be served by [`crate::boot::serve`].
+pub enum Listener {
+    /// Plain TCP
+    Tcp(TcpListener),
+    /// TLS over TCP
+    #[cfg(feature = "tls")]
+    Tls(TcpListener, TlsConfig),
+    /// Unix domain socket
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
+
+impl From<TcpListener> for Listener {
+    fn from(listener: TcpListener) -> Self {
+        Self::Tcp(listener)
+    }
+}
+
+/// Binds the configured listener.
+///
+/// # Errors
+///
+/// When the listener could not be bound, the error names the listener and
+/// the reason.
+pub async fn bind(config: &config::Listener, binding: &str, port: i32) -> Result<Listener> {
+    let addr = format!("{binding}:{port}");
+    match config {
+        config::Listener::Tcp => Ok(Listener::Tcp(bind_tcp("tcp", &addr).await?)),
+        #[cfg(feature = "tls")]
+        config::Listener::Tls(tls) => {
+            let tls = TlsConfig::load(tls)
+                .map_err(|err| Error::Message(format!("tls listener on {addr}: {err}")))?;
+            Ok(Listener::Tls(bind_tcp("tls", &addr).await?, tls))
+        }
+        #[cfg(not(feature = "tls"))]
+        config::Listener::Tls(_) => Err(Error::Message(format!(
+            "tls listener on {addr}: requires the `tls` feature of loco-rs"
+        ))),
+        #[cfg(unix)]
+        config::Listener::Unix(unix) => bind_unix(unix),
+        #[cfg(not(unix))]
+        config::Listener::Unix(unix) => Err(Error::Message(format!(
+            "unix listener on {}: unix domain sockets are not supported on this platform",
+            unix.path.display()
+        ))),
+    }
+}
+
+async fn bind_tcp(kind: &str, addr: &str) -> Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .map_err(|err| Error::Message(format!("could not bind {kind} listener on {addr}: {err}")))
+}
+
+#[cfg(unix)]
+fn bind_unix(config: &config::UnixListener) -> Result<Listener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    let path = &config.path;
+    let fail =
+        |reason: String| Error::Message(format!("unix listener on {}: {reason}", path.display()));
+
+    let mode = u32::from_str_radix(&config.permissions, 8).map_err(|_| {
+        fail(format!(
+            "invalid socket permissions `{}`, expected an octal mode such as `660`",
+            config.permissions
+        ))
+    })?;
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(fail("path exists and is not a socket".to_string()));
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(fail("socket is in use by another process".to_string()));
+        }
+        info!(path = %path.display(), "removing stale unix socket");
+        std::fs::remove_file(path).map_err(|err| fail(err.to_string()))?;
+    }
+
+    let listener = tokio::net::UnixListener::bind(path)
+        .map_err(|err| fail(format!("could not bind: {err}")))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .map_err(|err| fail(format!("could not set permissions: {err}")))?;
+
+    Ok(Listener::Unix(listener, path.clone()))
+}
+
+/// A TLS acceptor that can be swapped when the certificate is renewed.
+#[cfg(feature = "tls")]
+#[derive(Clone)]
+pub struct TlsConfig {
+    config: config::TlsListener,
+    acceptor: Arc<RwLock<TlsAcceptor>>,
+}
+
+#[cfg(feature = "tls")]
+impl TlsConfig {
+    /// Loads the certificate and key.
+    ///
+    /// # Errors
+    ///
+    /// When the certificate or the key could not be read or are invalid
+    pub fn load(config: &config::TlsListener) -> Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+            acceptor: Arc::new(RwLock::new(load_acceptor(config)?)),
+        })
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor
+            .read()
+            .expect("tls acceptor lock poisoned")
+            .clone()
+    }
+
+    /// Reloads the certificate and key. On failure the current certificate
+    /// is kept.
+    fn reload(&self) {
+        match load_acceptor(&self.config) {
+            Ok(acceptor) => {
+                *self.acceptor.write().expect("tls acceptor lock poisoned") = acceptor;
+                info!(cert = %self.config.cert.display(), "tls certificate reloaded");
+            }
+            Err(err) => {
+                warn!(error = %err, "could not reload tls certificate, keeping the current one");
+            }
+        }
+    }
+
+    fn modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        Some((modified(&self.config.cert)?, modified(&self.config.key)?))
+    }
+
+    /// Reloads the certificate on `SIGHUP` and when the certificate or key
+    /// files change, until the token is cancelled.
+    async fn watch(self, token: CancellationToken) {
+        let mut last_modified = self.modified();
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(self.config.watch_interval.max(1)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        #[cfg(unix)]
+        let mut hangup =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+
+        loop {
+            #[cfg(unix)]
+            let hangup_received = async {
+                match hangup.as_mut() {
+                    Some(hangup) => hangup.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            #[cfg(not(unix))]
+            let hangup_received = std::future::pending::<Option<()>>();
+
+            tokio::select! {
+                () = token.cancelled() => return,
+                _ = hangup_received => {
+                    info!("SIGHUP received, reloading tls certificate");
+                    self.reload();
+                    last_modified = self.modified();
+                }
+                _ = interval.tick() => {
+                    let modified = self.modified();
+                    if modified.is_some() && modified != last_modified {
+                        self.reload();
+                        last_modified = modified;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+fn load_acceptor(config: &config::TlsListener) -> Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(&config.cert)
+        .and_then(Iterator::collect)
+        .map_err(|err| {
+            Error::Message(format!(
+                "could not read certificate {}: {err}",
+                config.cert.display()
+            ))
+        })?;
+    let key = PrivateKeyDer::from_pem_file(&config.key).map_err(|err| {
+        Error::Message(format!(
+            "could not read private key {}: {err}",
+            config.key.display()
+        ))
+    })?;
+
+    let mut server_config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| Error::Message(format!("invalid certificate or key: {err}")))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// The settings shared by all the connections, built from
+/// [`config::Connection`].
+#[derive(Clone)]
+pub(crate) struct ConnectionSettings {
+    builder: auto::Builder<TokioExecutor>,
+    limit: Option<Arc<Semaphore>>,
+    /// How long a client has to complete the TLS handshake, the request
+    /// headers read timeout.
+    #[cfg(feature = "tls")]
+    handshake_timeout: Duration,
+}
+
+/// The hyper default request headers read timeout.
+#[cfg(feature = "tls")]
+const DEFAULT_HEADER_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl ConnectionSettings {
+    pub(crate) fn new(config: &config::Connection) -> Self {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+
+        let mut http1 = builder.http1();
+        http1.timer(TokioTimer::new()).keep_alive(config.keep_alive);
+        if let Some(timeout) = config.header_read_timeout {
+            http1.header_read_timeout(Duration::from_millis(timeout));
+        }
+
+        let mut http2 = builder.http2();
+        http2.timer(TokioTimer::new());
+        if let Some(interval) = config.keep_alive_interval {
+            http2.keep_alive_interval(Duration::from_millis(interval));
+        }
+        if let Some(timeout) = config.keep_alive_timeout {
+            http2.keep_alive_timeout(Duration::from_millis(timeout));
+        }
+
+        Self {
+            builder,
+            limit: config
+                .max_connections
+                .map(|max| Arc::new(Semaphore::new(max))),
+            #[cfg(feature = "tls")]
+            handshake_timeout: config
+                .header_read_timeout
+                .map_or(DEFAULT_HEADER_READ_TIMEOUT, Duration::from_millis),
+        }
+    }
+}
+
+/// Serves connections until the token is cancelled, then waits for the open
+/// connections to finish their in-flight requests. A unix socket file is
+/// removed once the listener is closed.
+///
+/// On TCP and TLS listeners, the requests carry the peer address as a
+/// [`ConnectInfo<SocketAddr>`] extension.
+pub(crate) async fn serve(
+    listener: Listener,
+    router: Router,
+    settings: ConnectionSettings,
+    token: CancellationToken,
+) {
+    let connections = TaskTracker::new();
+
+    match listener {
+        Listener::Tcp(listener) => {
+            accept_loop(
+                &token,
+                settings.limit.clone(),
+                || listener.accept(),
+                |stream, peer, permit| {
+                    let conn =
+                        serve_connection(stream, Some(peer), router.clone(), &settings, &token);
+                    connections.spawn(async move {
+                        conn.await;
+                        drop(permit);
+                    });
+                },
+            )
+            .await;
+        }
+        #[cfg(feature = "tls")]
+        Listener::Tls(listener, tls) => {
+            tokio::spawn(tls.clone().watch(token.clone()));
+            accept_loop(
+                &token,
+                settings.limit.clone(),
+                || listener.accept(),
+                |stream, peer, permit| {
+                    let acceptor = tls.acceptor();
+                    let router = router.clone();
+                    let settings = settings.clone();
+                    let token = token.clone();
+                    connections.spawn(async move {
+                        let handshake = tokio::time::timeout(
+                            settings.handshake_timeout,
+                            acceptor.accept(stream),
+                        );
+                        tokio::select! {
+                            () = token.cancelled() => debug!("tls handshake aborted on shutdown"),
+                            res = handshake => match res {
+                                Ok(Ok(stream)) => {
+                                    serve_connection(stream, Some(peer), router, &settings, &token)
+                                        .await;
+                                }
+                                Ok(Err(err)) => debug!(error = %err, "tls handshake failed"),
+                                Err(_) => debug!("tls handshake timed out"),
+                            },
+                        }
+                        drop(permit);
+                    });
+                },
+            )
+            .await;
+        }
+        #[cfg(unix)]
+        Listener::Unix(listener, path) => {
+            accept_loop(
+                &token,
+                settings.limit.clone(),
+                || listener.accept(),
+                |stream, _, permit| {
+                    let conn = serve_connection(stream, None, router.clone(), &settings, &token);
+                    connections.spawn(async move {
+                        conn.await;
+                        drop(permit);
+                    });
+                },
+            )
+            .await;
+            drop(listener);
+            if let Err(err) = std::fs::remove_file(&path) {
+                warn!(path = %path.display(), error = %err, "could not remove unix socket");
+            }
+        }
+    }
+
+    connections.close();
+    connections.wait().await;
+}
+
+/// Accepts connections until the token is cancelled. When the number of
+/// connections is limited, waits for a free slot before accepting.
+async fn accept_loop<S, A, F>(
+    token: &CancellationToken,
+    limit: Option<Arc<Semaphore>>,
+    mut accept: impl FnMut() -> F,
+    mut on_connection: impl FnMut(S, A, Option<OwnedSemaphorePermit>),
+) where
+    F: std::future::Future<Output = std::io::Result<(S, A)>>,
+{
+    loop {
+        let permit = match &limit {
+            Some(limit) => tokio::select! {
+                () = token.cancelled() => return,
+                permit = limit.clone().acquire_owned() => permit.ok(),
+            },
+            None => None,
+        };
+
+        tokio::select! {
+            () = token.cancelled() => return,
+            res = accept() => match res {
+                Ok((stream, peer)) => on_connection(stream, peer, permit),
+                Err(err) => {
+                    warn!(error = %err, "could not accept connection");
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            },
+        }
+    }
+}
+
+/// Serves a single connection, shutting it down gracefully once the token is
+/// cancelled.
+fn serve_connection<I>(
+    io: I,
+    peer: Option<SocketAddr>,
+    router: Router,
+    settings: &ConnectionSettings,
+    token: &CancellationToken,
+) -> impl std::future::Future<Output = ()> + Send + 'static
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let builder = settings.builder.clone();
+    let token = token.clone();
+    let service = service_fn(move |mut request: Request<Incoming>| {
+        if let Some(peer) = peer {
+            request.extensions_mut().insert(ConnectInfo(peer));
+        }
+        router.clone().call(request)
+    });
+    async move {
+        let conn = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+        tokio::pin!(conn);
+
+        let res = tokio::select! {
+            res = conn.as_mut() => res,
+            () = token.cancelled() => {
+                conn.as_mut().graceful_shutdown();
+                conn.await
+            }
+        };
+        if let Err(err) = res {
+            debug!(error = %err, "connection closed with error");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, routing::get};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    #[cfg(feature = "tls")]
+    use tokio_rustls::{
+        rustls::{pki_types::ServerName, ClientConfig, RootCertStore},
+        TlsConnector,
+    };
+
+    use super::*;
+    use crate::{boot, tests_cfg};
+
+    const CERT: &str = "tests/fixtures/tls/cert.pem";
+    const KEY: &str = "tests/fixtures/tls/key.pem";
+
+    fn router() -> Router {
+        Router::new().route("/", get(|| async { "loco" }))
+    }
+
+    /// Serves the router on the listener until the returned token is
+    /// cancelled.
+    async fn spawn_server(
+        listener: Listener,
+    ) -> (CancellationToken, tokio::task::JoinHandle<Result<()>>) {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let token = ctx.cancellation_token.clone();
+        let handle = tokio::spawn(async move { boot::serve(listener, router(), &ctx).await });
+        (token, handle)
+    }
+
+    /// Requests `/` over TLS, trusting the fixture certificate.
+    #[cfg(feature = "tls")]
+    async fn tls_get(addr: std::net::SocketAddr) -> String {
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(CERT).unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let connector = TlsConnector::from(Arc::new(
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        ));
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn can_serve_tls() {
+        let config = config::Listener::Tls(config::TlsListener {
+            cert: CERT.into(),
+            key: KEY.into(),
+            watch_interval: 10_000,
+        });
+        let listener = bind(&config, "127.0.0.1", 0).await.unwrap();
+        let Listener::Tls(tcp, _) = &listener else {
+            panic!("expected a tls listener");
+        };
+        let addr = tcp.local_addr().unwrap();
+        let (token, handle) = spawn_server(listener).await;
+
+        let response = tls_get(addr).await;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("loco"));
+
+        token.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn tls_handshake_times_out() {
+        let config = config::Listener::Tls(config::TlsListener {
+            cert: CERT.into(),
+            key: KEY.into(),
+            watch_interval: 10_000,
+        });
+        let listener = bind(&config, "127.0.0.1", 0).await.unwrap();
+        let Listener::Tls(tcp, _) = &listener else {
+            panic!("expected a tls listener");
+        };
+        let addr = tcp.local_addr().unwrap();
+        let settings = ConnectionSettings::new(&config::Connection {
+            max_connections: Some(1),
+            header_read_timeout: Some(100),
+            ..Default::default()
+        });
+        let token = CancellationToken::new();
+        let handle = tokio::spawn(serve(listener, router(), settings, token.clone()));
+
+        // never sends a ClientHello
+        let _silent = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let response = tokio::time::timeout(Duration::from_secs(2), tls_get(addr))
+            .await
+            .expect("silent client kept the connection permit");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        let _silent = tokio::net::TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        token.cancel();
+        tokio::time::timeout(Duration::from_millis(50), handle)
+            .await
+            .expect("shutdown waited on a tls handshake")
+            .unwrap();
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn tls_bind_reports_invalid_certificate() {
+        let config = config::Listener::Tls(config::TlsListener {
+            cert: "tests/fixtures/tls/missing.pem".into(),
+            key: KEY.into(),
+            watch_interval: 10_000,
+        });
+
+        let Err(err) = bind(&config, "127.0.0.1", 0).await else {
+            panic!("expected a bind error");
+        };
+        let err = err.to_string();
+        assert!(err.contains("tls listener on 127.0.0.1:0"), "{err}");
+        assert!(err.contains("missing.pem"), "{err}");
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[tokio::test]
+    async fn tls_bind_requires_the_tls_feature() {
+        let config = config::Listener::Tls(config::TlsListener {
+            cert: CERT.into(),
+            key: KEY.into(),
+            watch_interval: 10_000,
+        });
+
+        let Err(err) = bind(&config, "127.0.0.1", 0).await else {
+            panic!("expected a bind error");
+        };
+        assert_eq!(
+            err.to_string(),
+            "tls listener on 127.0.0.1:0: requires the `tls` feature of loco-rs"
+        );
+    }
+
+    #[tokio::test]
+    async fn tcp_bind_reports_listener_and_reason() {
+        let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = i32::from(taken.local_addr().unwrap().port());
+
+        let Err(err) = bind(&config::Listener::Tcp, "127.0.0.1", port).await else {
+            panic!("expected a bind error");
+        };
+        assert!(err
+            .to_string()
+            .starts_with(&format!("could not bind tcp listener on 127.0.0.1:{port}")));
+    }
+
+    /// Serves the router on a local TCP port with the given connection
+    /// settings.
+    async fn spawn_tcp_server(
+        config: &config::Connection,
+    ) -> (std::net::SocketAddr, CancellationToken) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = CancellationToken::new();
+        tokio::spawn(serve(
+            Listener::Tcp(listener),
+            router(),
+            ConnectionSettings::new(config),
+            token.clone(),
+        ));
+        (addr, token)
+    }
+
+    /// Sends a keep-alive request.
+    async fn send(stream: &mut tokio::net::TcpStream) {
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+    }
+
+    /// Returns whether a response arrived in time.
+    async fn responds_within(stream: &mut tokio::net::TcpStream, millis: u64) -> bool {
+        let mut response = vec![];
+        let mut buf = [0; 1024];
+        tokio::time::timeout(Duration::from_millis(millis), async {
+            while !response.ends_with(b"loco") {
+                let read = stream.read(&mut buf).await.unwrap();
+                assert!(read > 0, "connection closed");
+                response.extend_from_slice(&buf[..read]);
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    #[tokio::test]
+    async fn can_limit_connections() {
+        let config = config::Connection {
+            max_connections: Some(1),
+            ..Default::default()
+        };
+        let (addr, token) = spawn_tcp_server(&config).await;
+
+        let mut first = tokio::net::TcpStream::connect(addr).await.unwrap();
+        send(&mut first).await;
+        assert!(responds_within(&mut first, 1_000).await);
+
+        // waits for the first connection to be closed
+        let mut second = tokio::net::TcpStream::connect(addr).await.unwrap();
+        send(&mut second).await;
+        assert!(!responds_within(&mut second, 300).await);
+
+        drop(first);
+        assert!(responds_within(&mut second, 1_000).await);
+
+        token.cancel();
+    }
+
+    #[tokio::test]
+    async fn requests_carry_the_peer_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = CancellationToken::new();
+        let router = Router::new().route(
+            "/",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.to_string() }),
+        );
+        tokio::spawn(serve(
+            Listener::Tcp(listener),
+            router,
+            ConnectionSettings::new(&config::Connection::default()),
+            token.clone(),
+        ));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let peer = stream.local_addr().unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.ends_with(&peer.to_string()), "{response}");
+        token.cancel();
+    }
+
+    #[tokio::test]
+    async fn can_limit_header_read_time() {
+        let config = config::Connection {
+            header_read_timeout: Some(100),
+            ..Default::default()
+        };
+        let (addr, token) = spawn_tcp_server(&config).await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let mut response = vec![];
+        let closed =
+            tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut response)).await;
+
+        assert!(closed.is_ok(), "slow headers connection was not closed");
+        token.cancel();
+    }
+
+    #[test]
+    fn can_use_connection_settings() {
+        let settings = ConnectionSettings::new(&config::Connection {
+            max_connections: Some(8),
+            ..Default::default()
+        });
+        assert_eq!(
+            settings.limit.map(|limit| limit.available_permits()),
+            Some(8)
+        );
+
+        assert!(ConnectionSettings::new(&config::Connection::default())
+            .limit
+            .is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn can_serve_unix_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("loco-{}.sock", uuid::Uuid::new_v4()));
+        // a socket file left behind by a previous run
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let config = config::Listener::Unix(config::UnixListener {
+            path: path.clone(),
+            permissions: "600".to_string(),
+        });
+        let listener = bind(&config, "127.0.0.1", 0).await.unwrap();
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        let (token, handle) = spawn_server(listener).await;
+
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+        let response = sender
+            .send_request(
+                Request::builder()
+                    .uri("/")
+                    .header("host", "localhost")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(Body::new(response.into_body()), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "loco");
+        drop(sender);
+
+        token.cancel();
+        handle.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_bind_refuses_non_socket_path() {
+        let config = config::Listener::Unix(config::UnixListener {
+            path: CERT.into(),
+            permissions: "660".to_string(),
+        });
+
+        let Err(err) = bind(&config, "127.0.0.1", 0).await else {
+            panic!("expected a bind error");
+        };
+        assert_eq!(
+            err.to_string(),
+            format!("unix listener on {CERT}: path exists and is not a socket")
+        );
+    }
+}
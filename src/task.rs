@@ -2,17 +2,52 @@
 //!
 //! This module defines the task management framework used to manage and execute
 //! tasks in a web server application.
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sidekiq::Worker;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-use crate::{app::AppContext, errors::Error, Result};
+use crate::{
+    app::{AppContext, Hooks},
+    environment::Environment,
+    errors::Error,
+    worker::AppWorker,
+    Result,
+};
+
+/// The tracing target of the task span and of the task lifecycle events.
+///
+/// Subscribers can route task output separately from request logs, e.g. the
+/// `loco::task=info` filter directive selects the lifecycle events, and the
+/// `[task]` span directive selects everything logged while a task runs.
+pub const TASK_LOG_TARGET: &str = "loco::task";
 
 /// Struct representing a collection of task arguments.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Vars {
     /// A list of cli arguments.
     pub cli: BTreeMap<String, String>,
+    /// Bare arguments, in the order given, see [`Vars::parse_argv`].
+    #[serde(default)]
+    pub positional: Vec<String>,
+    /// See [`Vars::with_correlation_id`]. Never deserialized, so callers
+    /// cannot set it through the arguments they send.
+    #[serde(skip)]
+    correlation_id: Option<String>,
 }
 
 impl Vars {
@@ -35,7 +70,77 @@ impl Vars {
     pub fn from_cli_args(args: Vec<(String, String)>) -> Self {
         Self {
             cli: args.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse raw command line arguments into [`Vars`].
+    ///
+    /// The supported forms are:
+    ///
+    /// * `--key value` and `--key=value`
+    /// * `key=value`
+    /// * `--flag`, followed by another `--` option or last, is set to `true`
+    /// * anything else is a positional argument, as is everything after a
+    ///   bare `--`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when an argument has an empty key, e.g. `--=value`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loco_rs::task::Vars;
+    ///
+    /// let argv = ["--user", "1", "--dry-run", "--format=csv", "since=2024", "report.csv"]
+    ///     .map(String::from);
+    /// let vars = Vars::parse_argv(&argv).unwrap();
+    ///
+    /// assert_eq!(vars.cli_arg("user").unwrap(), "1");
+    /// assert_eq!(vars.cli_arg("dry-run").unwrap(), "true");
+    /// assert_eq!(vars.cli_arg("format").unwrap(), "csv");
+    /// assert_eq!(vars.cli_arg("since").unwrap(), "2024");
+    /// assert_eq!(vars.positional, vec!["report.csv"]);
+    /// ```
+    pub fn parse_argv(argv: &[String]) -> Result<Self> {
+        let mut vars = Self::default();
+        let mut remaining = argv.iter().peekable();
+
+        while let Some(arg) = remaining.next() {
+            if arg == "--" {
+                vars.positional.extend(remaining.by_ref().cloned());
+                break;
+            }
+
+            let (key, value) = if let Some(option) = arg.strip_prefix("--") {
+                if let Some((key, value)) = option.split_once('=') {
+                    (key, value.to_string())
+                } else {
+                    let value = match remaining.peek() {
+                        Some(next) if !next.starts_with("--") => {
+                            remaining.next().cloned().unwrap_or_default()
+                        }
+                        _ => "true".to_string(),
+                    };
+                    (option, value)
+                }
+            } else if let Some((key, value)) = arg.split_once('=') {
+                (key, value.to_string())
+            } else {
+                vars.positional.push(arg.clone());
+                continue;
+            };
+
+            if key.is_empty() {
+                return Err(Error::Message(format!(
+                    "invalid argument `{arg}`: missing key"
+                )));
+            }
+            vars.cli.insert(key.to_string(), value);
         }
+
+        Ok(vars)
     }
 
     /// Retrieves the value associated with the given key from the `cli` list.
@@ -60,6 +165,104 @@ impl Vars {
             .get(key)
             .ok_or(Error::Message(format!("the argument {key} does not exist")))
     }
+
+    /// Retrieves a duration argument, written as a number followed by a unit
+    /// (`ms`, `s`, `m`, `h` or `d`), e.g. `30s`, `5m` or `1h30m`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key does not exist or is not a valid duration.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use loco_rs::task::Vars;
+    ///
+    /// let vars = Vars::from_cli_args(vec![("every".to_string(), "1h30m".to_string())]);
+    ///
+    /// assert_eq!(vars.cli_arg_duration("every").unwrap(), Duration::from_secs(5400));
+    /// ```
+    pub fn cli_arg_duration(&self, key: &str) -> Result<Duration> {
+        let value = self.cli_arg(key)?;
+        parse_duration(value).ok_or_else(|| {
+            Error::Message(format!(
+                "the argument {key} is not a valid duration: `{value}` (expected e.g. 30s, 5m, 1h)"
+            ))
+        })
+    }
+
+    /// Retrieves an ISO 8601 (RFC 3339) date and time argument, e.g.
+    /// `2024-03-01T12:00:00Z` or `2024-03-01T12:00:00+02:00`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key does not exist or is not a valid date and
+    /// time.
+    pub fn cli_arg_datetime(&self, key: &str) -> Result<chrono::DateTime<chrono::FixedOffset>> {
+        let value = self.cli_arg(key)?;
+        chrono::DateTime::parse_from_rfc3339(value).map_err(|err| {
+            Error::Message(format!(
+                "the argument {key} is not a valid ISO 8601 date and time: `{value}` ({err})"
+            ))
+        })
+    }
+
+    /// Set a correlation id, e.g. the id of the HTTP request that triggered
+    /// the task. The id is kept apart from the arguments and attached to the
+    /// task span, linking the task logs to the originating request.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loco_rs::{controller::middleware::request_id::RequestId, task::Vars};
+    ///
+    /// let request_id = RequestId::new();
+    /// let vars = Vars::default().with_correlation_id(request_id.get());
+    ///
+    /// assert_eq!(vars.correlation_id(), Some(request_id.get()));
+    /// ```
+    #[must_use]
+    pub fn with_correlation_id(mut self, id: &str) -> Self {
+        self.correlation_id = Some(id.to_string());
+        self
+    }
+
+    /// Get the correlation id, see [`Vars::with_correlation_id`].
+    #[must_use]
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+}
+
+/// Parses a humanized duration, e.g. `30s` or `1h30m`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let part = match &rest[..unit_len] {
+            "ms" => Duration::from_millis(amount),
+            "s" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount.checked_mul(60)?),
+            "h" => Duration::from_secs(amount.checked_mul(60 * 60)?),
+            "d" => Duration::from_secs(amount.checked_mul(24 * 60 * 60)?),
+            _ => return None,
+        };
+        total = total.checked_add(part)?;
+        rest = &rest[unit_len..];
+    }
+    Some(total)
 }
 
 /// Information about a task, including its name and details.
@@ -69,24 +272,518 @@ pub struct TaskInfo {
     pub detail: String,
 }
 
+impl TaskInfo {
+    /// The task namespace, the part of the name before the first `:`. For
+    /// example the namespace of `db:seed` is `db`.
+    #[must_use]
+    pub fn namespace(&self) -> Option<&str> {
+        self.name.split_once(':').map(|(namespace, _)| namespace)
+    }
+}
+
+/// A declared task argument, see [`Task::arg_schema`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskArg {
+    /// The argument name, as passed on the command line (`name:value`)
+    pub name: String,
+    /// What the argument is for
+    pub description: String,
+    /// Whether the task fails without it
+    pub required: bool,
+}
+
+impl TaskArg {
+    /// A required argument.
+    #[must_use]
+    pub fn required(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            required: true,
+        }
+    }
+
+    /// An optional argument.
+    #[must_use]
+    pub fn optional(name: &str, description: &str) -> Self {
+        Self {
+            required: false,
+            ..Self::required(name, description)
+        }
+    }
+}
+
+tokio::task_local! {
+    static CAPTURE: Arc<Mutex<(Vec<u8>, Vec<u8>)>>;
+}
+
+/// The standard output and error of a task run by
+/// [`Tasks::run_captured`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// A writer to the task standard output or error, see [`stdout`] and
+/// [`stderr`].
+#[derive(Debug)]
+pub struct TaskWriter {
+    stream: Stream,
+}
+
+impl Write for TaskWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let captured = CAPTURE.try_with(|capture| {
+            let mut capture = capture.lock().unwrap_or_else(PoisonError::into_inner);
+            match self.stream {
+                Stream::Stdout => capture.0.extend_from_slice(buf),
+                Stream::Stderr => capture.1.extend_from_slice(buf),
+            }
+        });
+        match (captured, self.stream) {
+            (Ok(()), _) => Ok(buf.len()),
+            (Err(_), Stream::Stdout) => io::stdout().write(buf),
+            (Err(_), Stream::Stderr) => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.stream {
+            Stream::Stdout => io::stdout().flush(),
+            Stream::Stderr => io::stderr().flush(),
+        }
+    }
+}
+
+/// The task standard output. Tasks printing their output through it, rather
+/// than with `println!`, can be tested with [`Tasks::run_captured`].
+///
+/// # Example
+///
+/// ```
+/// use std::io::Write;
+///
+/// writeln!(loco_rs::task::stdout(), "processed {} users", 3).unwrap();
+/// ```
+#[must_use]
+pub fn stdout() -> TaskWriter {
+    TaskWriter {
+        stream: Stream::Stdout,
+    }
+}
+
+/// The task standard error, see [`stdout`].
+#[must_use]
+pub fn stderr() -> TaskWriter {
+    TaskWriter {
+        stream: Stream::Stderr,
+    }
+}
+
+tokio::task_local! {
+    static EVENTS: mpsc::UnboundedSender<TaskEvent>;
+    static CANCELLATION: CancellationToken;
+}
+
+/// The cancellation token of the running task, triggered by [`Tasks::cancel`]
+/// or when the application shuts down.
+///
+/// Long-running tasks poll it to stop cleanly. Outside of a task run the token
+/// is never cancelled.
+///
+/// # Example
+///
+/// ```
+/// use loco_rs::task;
+///
+/// let token = task::cancellation_token();
+/// for batch in 0..10 {
+///     if token.is_cancelled() {
+///         break;
+///     }
+///     // process the batch
+/// }
+/// ```
+#[must_use]
+pub fn cancellation_token() -> CancellationToken {
+    CANCELLATION
+        .try_with(CancellationToken::clone)
+        .unwrap_or_default()
+}
+
+/// A structured event emitted by a running task with [`emit`], for live
+/// monitoring through [`Tasks::run_with_events`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskEvent {
+    /// The task processed `current` out of `total` items, when known
+    Progress { current: u64, total: Option<u64> },
+    /// A log line
+    Log { message: String },
+    /// The task entered a new phase
+    Phase { name: String },
+}
+
+/// Emit an event to the subscriber of the running task, if any. Events
+/// emitted outside of [`Tasks::run_with_events`] are dropped.
+///
+/// # Example
+///
+/// ```
+/// use loco_rs::task::{self, TaskEvent};
+///
+/// task::emit(TaskEvent::Progress { current: 1, total: Some(10) });
+/// ```
+pub fn emit(event: TaskEvent) {
+    let _ = EVENTS.try_with(|events| events.send(event));
+}
+
 /// A trait defining the behavior of a task.
 #[async_trait]
 pub trait Task<T: Send + Sync + Clone>: Send + Sync {
     /// Get information about the task.
     fn task(&self) -> TaskInfo;
+
+    /// Declare the arguments the task reads from [`Vars`], used for
+    /// documentation.
+    fn arg_schema(&self) -> Vec<TaskArg> {
+        vec![]
+    }
+
+    /// Whether overlapping runs of this task are safe. The [`Scheduler`]
+    /// never fires a task that is not concurrency safe while a previous run
+    /// is still in flight.
+    ///
+    /// [`Scheduler`]: crate::scheduler::Scheduler
+    fn concurrency_safe(&self) -> bool {
+        false
+    }
+
+    /// The permission a caller needs to run the task over HTTP, see
+    /// [`crate::controller::tasks`]. `None` requires the `tasks:<name>`
+    /// permission.
+    fn required_permission(&self) -> Option<String> {
+        None
+    }
+
+    /// The environments the task may run in, `None` allowing all of them.
+    /// Restrict development-only tasks (e.g. loading fake data) so they are
+    /// refused in production.
+    fn allowed_environments(&self) -> Option<Vec<Environment>> {
+        None
+    }
+
+    /// A hint of how long the task usually runs, exposed in the
+    /// [`Tasks::list_json`] catalog so tooling can warn before running a
+    /// long task. Never enforced.
+    fn estimated_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Declare the JSON schema of the output returned by
+    /// [`Task::run_with_output`], so tooling can render it generically. It is
+    /// exposed in the [`Tasks::list_json`] catalog.
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Describe the side effects a run with `vars` would have, e.g.
+    /// `delete 120 expired sessions`. Logged instead of running the task
+    /// when the registry is in dry-run mode, see [`Tasks::set_dry_run`].
+    fn describe_effects(&self, _vars: &Vars) -> Vec<String> {
+        vec![]
+    }
+
     /// Execute the task with the provided application context and variables.
     async fn run(&self, app_context: &AppContext<T>, vars: &Vars) -> Result<()>;
+
+    /// Execute the task and return its output, e.g. a report, handed to
+    /// programmatic callers by [`Tasks::run_detailed`]. Defaults to
+    /// [`Task::run`], without output.
+    async fn run_with_output(
+        &self,
+        app_context: &AppContext<T>,
+        vars: &Vars,
+    ) -> Result<Option<serde_json::Value>> {
+        self.run(app_context, vars).await.map(|()| None)
+    }
+
+    /// Invoked only after the task succeeded, with its output, for
+    /// success-only side effects such as invalidating caches or notifying
+    /// other systems. An error fails the task run.
+    async fn on_success(
+        &self,
+        _app_context: &AppContext<T>,
+        _output: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// How a task run ended, see [`TaskRunResult`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "error", rename_all = "snake_case")]
+pub enum TaskStatus {
+    Succeeded,
+    /// The task returned an error, with its message
+    Failed(String),
+}
+
+/// How the delay between retries grows, see [`RetryPolicy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackoffStrategy {
+    /// Always wait `base_delay`
+    Fixed,
+    /// Double the delay after every attempt
+    Exponential,
+    /// Double the delay after every attempt, randomized by `jitter` so
+    /// retrying runs do not all fire at once
+    #[default]
+    ExponentialWithJitter,
+}
+
+/// How [`Tasks::run_with_retry`] retries a failing task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// How many times the task runs at most, the first run included
+    pub max_attempts: u32,
+    /// The delay before the first retry
+    pub base_delay: Duration,
+    /// The delay never grows past this
+    pub max_delay: Duration,
+    /// With [`BackoffStrategy::ExponentialWithJitter`], the fraction
+    /// (`0.0` to `1.0`) of the delay that is randomized: the delay is picked
+    /// between `delay * (1 - jitter)` and `delay`. Values out of range are
+    /// clamped, and non-finite values disable the jitter.
+    pub jitter: f64,
+    pub backoff_strategy: BackoffStrategy,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 1.0,
+            backoff_strategy: BackoffStrategy::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay to wait after the given failed attempt (starting at `1`)
+    /// before retrying.
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = match self.backoff_strategy {
+            BackoffStrategy::Fixed => self.base_delay,
+            BackoffStrategy::Exponential | BackoffStrategy::ExponentialWithJitter => self
+                .base_delay
+                .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1))),
+        }
+        .min(self.max_delay);
+
+        if self.backoff_strategy == BackoffStrategy::ExponentialWithJitter {
+            let jitter = if self.jitter.is_finite() {
+                self.jitter.clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            delay.mul_f64(1.0 - jitter * rand::thread_rng().gen::<f64>())
+        } else {
+            delay
+        }
+    }
+}
+
+/// The outcome of [`Tasks::run_detailed`].
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskRunResult {
+    /// The task name
+    pub name: String,
+    /// How long the task ran
+    pub duration: Duration,
+    pub status: TaskStatus,
+    /// The output returned by [`Task::run_with_output`]
+    pub output: Option<serde_json::Value>,
+}
+
+impl TaskRunResult {
+    /// Whether the task succeeded.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.status == TaskStatus::Succeeded
+    }
+}
+
+/// A global pre-check run before a batch of tasks, see
+/// [`Tasks::set_guard`].
+///
+/// Any `Fn(&AppContext<T>) -> Result<()>` closure is a guard.
+#[async_trait]
+pub trait TaskGuard<T: Send + Sync + Clone>: Send + Sync {
+    /// Check the environment, returning an error aborts the batch.
+    async fn check(&self, app_context: &AppContext<T>) -> Result<()>;
+}
+
+#[async_trait]
+impl<T, F> TaskGuard<T> for F
+where
+    T: Send + Sync + Clone,
+    F: Fn(&AppContext<T>) -> Result<()> + Send + Sync,
+{
+    async fn check(&self, app_context: &AppContext<T>) -> Result<()> {
+        self(app_context)
+    }
+}
+
+/// The accumulated runs of a task, see [`Tasks::metrics_snapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskStats {
+    /// How many times the task ran
+    pub runs: u64,
+    /// How many of the runs failed
+    pub failures: u64,
+    /// When the last run finished
+    pub last_run_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// How long the last run took
+    pub last_duration: Option<Duration>,
+}
+
+/// A finished task run, see [`TaskHistory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskRunRecord {
+    /// The task name
+    pub task: String,
+    /// When the run finished
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    /// How long the run took
+    pub duration: Duration,
+    /// Whether the run succeeded
+    pub success: bool,
+}
+
+/// How many runs a [`TaskHistory`] keeps by default.
+pub const DEFAULT_TASK_HISTORY_LEN: usize = 1_000;
+
+/// The history of the task runs of a [`Tasks`] registry, kept in memory for
+/// the lifetime of the process. Handles are cheap to clone and share the same
+/// entries, see [`Tasks::history`].
+///
+/// The history keeps the last [`DEFAULT_TASK_HISTORY_LEN`] runs, dropping the
+/// oldest ones, see [`TaskHistory::set_max_len`]. To also drop the runs past
+/// a retention period, register a [`PruneTaskHistoryTask`] and run it on a
+/// schedule.
+#[derive(Debug, Clone)]
+pub struct TaskHistory {
+    entries: Arc<Mutex<VecDeque<TaskRunRecord>>>,
+    max_len: Arc<AtomicUsize>,
+}
+
+impl Default for TaskHistory {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+            max_len: Arc::new(AtomicUsize::new(DEFAULT_TASK_HISTORY_LEN)),
+        }
+    }
+}
+
+impl TaskHistory {
+    /// Record a finished run, dropping the oldest run when the history is
+    /// full.
+    pub fn record(&self, record: TaskRunRecord) {
+        let max_len = self.max_len.load(Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.push_back(record);
+        while entries.len() > max_len {
+            entries.pop_front();
+        }
+        drop(entries);
+    }
+
+    /// Keep at most the last `max_len` runs, dropping the older ones. `0`
+    /// turns the recording off.
+    pub fn set_max_len(&self, max_len: usize) {
+        self.max_len.store(max_len, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        while entries.len() > max_len {
+            entries.pop_front();
+        }
+        drop(entries);
+    }
+
+    /// All the recorded runs, oldest first.
+    #[must_use]
+    pub fn entries(&self) -> Vec<TaskRunRecord> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Remove the runs that finished before `cutoff`, returning how many were
+    /// removed.
+    pub fn prune_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let len = entries.len();
+        entries.retain(|entry| entry.finished_at >= cutoff);
+        len - entries.len()
+    }
+}
+
+/// A single task of a batch run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTask {
+    /// The registered task name
+    pub name: String,
+    /// The task arguments
+    #[serde(default)]
+    pub vars: Vars,
+}
+
+impl BatchTask {
+    #[must_use]
+    pub fn new(name: &str, vars: Vars) -> Self {
+        Self {
+            name: name.to_string(),
+            vars,
+        }
+    }
 }
 
 /// Managing and running tasks.
 pub struct Tasks<T: Send + Sync + Clone> {
     registry: BTreeMap<String, Box<dyn Task<T>>>,
+    guard: Option<Box<dyn TaskGuard<T>>>,
+    stats: Mutex<BTreeMap<String, TaskStats>>,
+    history: TaskHistory,
+    dry_run: bool,
+    running: Mutex<BTreeMap<u64, (String, CancellationToken)>>,
+    next_run_id: AtomicU64,
 }
 
 impl<T: Send + Sync + Clone> Default for Tasks<T> {
     fn default() -> Self {
         Self {
             registry: Default::default(),
+            guard: None,
+            stats: Mutex::default(),
+            history: TaskHistory::default(),
+            dry_run: false,
+            running: Mutex::default(),
+            next_run_id: AtomicU64::new(0),
         }
     }
 }
@@ -98,19 +795,303 @@ impl<T: Send + Sync + Clone> Tasks<T> {
         self.registry.values().map(|t| t.task()).collect::<Vec<_>>()
     }
 
+    /// Whether a registered task is concurrency safe, see
+    /// [`Task::concurrency_safe`]. `None` when the task is not found.
+    #[must_use]
+    pub fn is_concurrency_safe(&self, task: &str) -> Option<bool> {
+        self.registry.get(task).map(|task| task.concurrency_safe())
+    }
+
+    /// The permission needed to run a registered task over HTTP, see
+    /// [`Task::required_permission`].
+    ///
+    /// # Errors
+    ///
+    /// When the task is not found.
+    pub fn required_permission(&self, task: &str) -> Result<Option<String>> {
+        self.registry
+            .get(task)
+            .map(|task| task.required_permission())
+            .ok_or_else(|| Error::TaskNotFound(task.to_string()))
+    }
+
+    /// The accumulated run counts and last runs of the tasks that ran since
+    /// the registry was created, a lightweight alternative to exporting
+    /// metrics.
+    #[must_use]
+    pub fn metrics_snapshot(&self) -> BTreeMap<String, TaskStats> {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// A handle to the history of the task runs, e.g. to hand to a
+    /// [`PruneTaskHistoryTask`].
+    #[must_use]
+    pub fn history(&self) -> TaskHistory {
+        self.history.clone()
+    }
+
+    /// Render the task catalog as a Markdown table, with the tasks names,
+    /// namespaces, details and declared arguments.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut markdown =
+            String::from("| Task | Namespace | Detail | Arguments |\n| --- | --- | --- | --- |\n");
+        for task in self.registry.values() {
+            let info = task.task();
+            let args = task
+                .arg_schema()
+                .iter()
+                .map(|arg| {
+                    format!(
+                        "`{}` ({}): {}",
+                        arg.name,
+                        if arg.required { "required" } else { "optional" },
+                        arg.description
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("<br>");
+            // writing to a `String` cannot fail
+            let _ = writeln!(
+                markdown,
+                "| `{}` | {} | {} | {} |",
+                info.name,
+                info.namespace()
+                    .map_or_else(String::new, |ns| format!("`{ns}`")),
+                markdown_cell(&info.detail),
+                markdown_cell(&args),
+            );
+        }
+        markdown
+    }
+
+    /// An example command line running a task, with a placeholder for each
+    /// declared argument (see [`Task::arg_schema`]). Optional arguments are
+    /// in brackets, e.g.
+    /// `cargo loco task db:seed path:<path> [reset:<reset>]`.
+    ///
+    /// # Errors
+    ///
+    /// When the task is not found.
+    pub fn usage_example(&self, name: &str) -> Result<String> {
+        use std::fmt::Write as _;
+
+        let task = self
+            .registry
+            .get(name)
+            .ok_or_else(|| Error::TaskNotFound(name.to_string()))?;
+
+        let mut example = format!("cargo loco task {name}");
+        for arg in task.arg_schema() {
+            // writing to a `String` cannot fail
+            let _ = if arg.required {
+                write!(example, " {0}:<{0}>", arg.name)
+            } else {
+                write!(example, " [{0}:<{0}>]", arg.name)
+            };
+        }
+        Ok(example)
+    }
+
+    /// The task catalog as JSON, for tooling: the tasks names, namespaces,
+    /// details, declared arguments, output schemas and estimated durations
+    /// (in seconds).
+    #[must_use]
+    pub fn list_json(&self) -> serde_json::Value {
+        self.registry
+            .values()
+            .map(|task| {
+                let info = task.task();
+                serde_json::json!({
+                    "name": info.name,
+                    "namespace": info.namespace(),
+                    "detail": info.detail,
+                    "args": task.arg_schema(),
+                    "output_schema": task.output_schema(),
+                    "estimated_duration_secs": task
+                        .estimated_duration()
+                        .map(|duration| duration.as_secs()),
+                })
+            })
+            .collect()
+    }
+
     /// Run a registered task by name with provided variables.
     ///
     /// # Errors
     ///
     /// Returns a [`Result`] if an task finished with error. mostly if the given
     /// task is not found or an error to run the task.s
+    ///
+    /// The task runs inside a `task` span, carrying the task name and the
+    /// [`Vars::correlation_id`] when set. The span and the task lifecycle
+    /// events are logged under the [`TASK_LOG_TARGET`] target.
     pub async fn run(&self, app_context: &AppContext<T>, task: &str, vars: &Vars) -> Result<()> {
+        let (res, _) = self.run_timed(app_context, task, vars).await?;
+        res.map(|_| ())
+    }
+
+    /// Run a registered task by name like [`Tasks::run`], reporting how long
+    /// it ran, how it ended and its output.
+    ///
+    /// # Errors
+    ///
+    /// When the task is not found or not allowed in the current environment
+    /// (see [`Task::allowed_environments`]). A task finishing with error is
+    /// reported as [`TaskStatus::Failed`].
+    pub async fn run_detailed(
+        &self,
+        app_context: &AppContext<T>,
+        task: &str,
+        vars: &Vars,
+    ) -> Result<TaskRunResult> {
+        let (res, duration) = self.run_timed(app_context, task, vars).await?;
+        let (status, output) = match res {
+            Ok(output) => (TaskStatus::Succeeded, output),
+            Err(err) => (TaskStatus::Failed(err.to_string()), None),
+        };
+        Ok(TaskRunResult {
+            name: task.to_string(),
+            duration,
+            status,
+            output,
+        })
+    }
+
+    /// Run a registered task by name like [`Tasks::run_detailed`], capturing
+    /// what it writes to [`stdout`] and [`stderr`] instead of printing it.
+    ///
+    /// Only the writes made from the task future are captured, not the ones
+    /// from tasks it spawns.
+    ///
+    /// # Errors
+    ///
+    /// When the task is not found.
+    pub async fn run_captured(
+        &self,
+        app_context: &AppContext<T>,
+        task: &str,
+        vars: &Vars,
+    ) -> Result<(TaskRunResult, CapturedOutput)> {
+        let capture = Arc::new(Mutex::new((vec![], vec![])));
+        let result = CAPTURE
+            .scope(capture.clone(), self.run_detailed(app_context, task, vars))
+            .await?;
+
+        let (stdout, stderr) = &*capture.lock().unwrap_or_else(PoisonError::into_inner);
+        let output = CapturedOutput {
+            stdout: String::from_utf8_lossy(stdout).to_string(),
+            stderr: String::from_utf8_lossy(stderr).to_string(),
+        };
+        Ok((result, output))
+    }
+
+    /// Run a registered task by name like [`Tasks::run_detailed`], sending the
+    /// events it emits with [`emit`] to `events`, in order.
+    ///
+    /// Only the events emitted from the task future are sent, not the ones
+    /// from tasks it spawns.
+    ///
+    /// # Errors
+    ///
+    /// When the task is not found.
+    pub async fn run_with_events(
+        &self,
+        app_context: &AppContext<T>,
+        task: &str,
+        vars: &Vars,
+        events: mpsc::UnboundedSender<TaskEvent>,
+    ) -> Result<TaskRunResult> {
+        EVENTS
+            .scope(events, self.run_detailed(app_context, task, vars))
+            .await
+    }
+
+    /// Run a task in its span, returning the task result and duration. Fails
+    /// only when the task is not found.
+    async fn run_timed(
+        &self,
+        app_context: &AppContext<T>,
+        task: &str,
+        vars: &Vars,
+    ) -> Result<(Result<Option<serde_json::Value>>, Duration)> {
+        let task_span = tracing::span!(
+            target: TASK_LOG_TARGET,
+            tracing::Level::DEBUG,
+            "task",
+            task,
+            correlation_id = vars.correlation_id()
+        );
         let task = self
             .registry
             .get(task)
             .ok_or_else(|| Error::TaskNotFound(task.to_string()))?;
-        task.run(app_context, vars).await?;
-        Ok(())
+        check_environment(task.as_ref(), &app_context.environment)?;
+
+        if self.dry_run {
+            let _enter = task_span.enter();
+            let effects = task.describe_effects(vars);
+            tracing::info!(target: TASK_LOG_TARGET, effects = effects.len(), "task dry run");
+            for effect in effects {
+                tracing::info!(target: TASK_LOG_TARGET, effect = %effect, "planned effect");
+            }
+            return Ok((Ok(None), Duration::ZERO));
+        }
+
+        let name = task.task().name;
+        record_arg_count(&name, vars);
+        let token = app_context.cancellation_token.child_token();
+        let run_id = self.next_run_id.fetch_add(1, Ordering::Relaxed);
+        self.running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(run_id, (name.clone(), token.clone()));
+        let _running = RunningGuard {
+            running: &self.running,
+            run_id,
+        };
+
+        let started = Instant::now();
+        let res = async {
+            tracing::info!(target: TASK_LOG_TARGET, "task started");
+            let mut res = task.run_with_output(app_context, vars).await;
+            if let Ok(output) = &res {
+                if let Err(err) = task.on_success(app_context, output.as_ref()).await {
+                    res = Err(err);
+                }
+            }
+            match &res {
+                Ok(_) => tracing::info!(target: TASK_LOG_TARGET, "task finished"),
+                Err(err) => tracing::error!(target: TASK_LOG_TARGET, error = %err, "task failed"),
+            }
+            res
+        }
+        .instrument(task_span);
+        let res = CANCELLATION.scope(token, res).await;
+        let duration = started.elapsed();
+        let finished_at = chrono::Utc::now();
+
+        let mut all_stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+        let stats = all_stats.entry(name.clone()).or_default();
+        stats.runs += 1;
+        if res.is_err() {
+            stats.failures += 1;
+        }
+        stats.last_run_at = Some(finished_at);
+        stats.last_duration = Some(duration);
+        drop(all_stats);
+        self.history.record(TaskRunRecord {
+            task: name,
+            finished_at,
+            duration,
+            success: res.is_ok(),
+        });
+        Ok((res, duration))
     }
 
     /// Register a new task to the registry.
@@ -118,4 +1099,2048 @@ impl<T: Send + Sync + Clone> Tasks<T> {
         let name = task.task().name;
         self.registry.insert(name, Box::new(task));
     }
+
+    /// Cancel the in-flight runs of a task, through the token returned by
+    /// [`cancellation_token`] inside the task. Cooperative tasks stop at their
+    /// next check, others run to completion. Returns whether the task was
+    /// running.
+    pub fn cancel(&self, task: &str) -> bool {
+        let running = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut cancelled = false;
+        for (name, token) in running.values() {
+            if name == task {
+                token.cancel();
+                cancelled = true;
+            }
+        }
+        drop(running);
+        cancelled
+    }
+
+    /// Set the dry-run mode. In dry-run mode the tasks are not run: the side
+    /// effects each task declares in [`Task::describe_effects`] are logged
+    /// instead, a safe preview of risky maintenance. Dry runs are not
+    /// recorded in the stats and history.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Set a guard invoked before every batch run. When the guard returns an
+    /// error the whole batch is aborted before any task runs, which is a
+    /// safety net for dangerous operations (e.g. making sure the app is not
+    /// pointing at the production database).
+    pub fn set_guard(&mut self, guard: impl TaskGuard<T> + 'static) {
+        self.guard = Some(Box::new(guard));
+    }
+
+    /// A batch of all the tasks of a namespace (see [`TaskInfo::namespace`]),
+    /// in name order.
+    #[must_use]
+    pub fn namespace_batch(&self, namespace: &str) -> Vec<BatchTask> {
+        self.list()
+            .iter()
+            .filter(|info| info.namespace() == Some(namespace))
+            .map(|info| BatchTask::new(&info.name, Vars::default()))
+            .collect()
+    }
+
+    /// A batch of `count` tasks picked at random from a namespace, e.g. for
+    /// staggered maintenance or chaos tooling. The whole namespace is picked
+    /// when it has no more than `count` tasks.
+    ///
+    /// Passing a `seed` makes the selection reproducible.
+    #[must_use]
+    pub fn sample_batch(&self, namespace: &str, count: usize, seed: Option<u64>) -> Vec<BatchTask> {
+        let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        let mut batch = self.namespace_batch(namespace);
+        let (sample, _) = batch.partial_shuffle(&mut rng, count);
+        sample.to_vec()
+    }
+
+    /// Run a batch of tasks one after the other, stopping at the first
+    /// failure.
+    ///
+    /// # Errors
+    ///
+    /// When the guard rejects the batch, a task is not found or a task
+    /// finished with error.
+    pub async fn run_many(&self, app_context: &AppContext<T>, batch: &[BatchTask]) -> Result<()> {
+        if let Some(guard) = &self.guard {
+            if let Err(err) = guard.check(app_context).await {
+                tracing::error!(error = %err, "task guard rejected the batch");
+                return Err(err);
+            }
+        }
+
+        for task in batch {
+            self.run(app_context, &task.name, &task.vars).await?;
+        }
+        Ok(())
+    }
+
+    /// Run a batch of tasks like [`Tasks::run_many`], bounding the whole
+    /// batch by `deadline`. When the deadline passes the running task is
+    /// aborted and the remaining ones are not started.
+    ///
+    /// # Errors
+    ///
+    /// Like [`Tasks::run_many`], and when the batch exceeds the deadline,
+    /// with an error naming the tasks that completed.
+    pub async fn run_many_with_timeout(
+        &self,
+        app_context: &AppContext<T>,
+        batch: &[BatchTask],
+        deadline: Duration,
+    ) -> Result<()> {
+        if let Some(guard) = &self.guard {
+            if let Err(err) = guard.check(app_context).await {
+                tracing::error!(error = %err, "task guard rejected the batch");
+                return Err(err);
+            }
+        }
+
+        let mut completed = vec![];
+        let res = tokio::time::timeout(deadline, async {
+            for task in batch {
+                self.run(app_context, &task.name, &task.vars).await?;
+                completed.push(task.name.as_str());
+            }
+            Ok(())
+        })
+        .await;
+
+        res.unwrap_or_else(|_| {
+            let completed = if completed.is_empty() {
+                "none".to_string()
+            } else {
+                completed.join(", ")
+            };
+            tracing::error!(?deadline, completed = %completed, "task batch timed out");
+            Err(Error::Message(format!(
+                "task batch timed out after {deadline:?}, completed tasks: {completed}"
+            )))
+        })
+    }
+
+    /// Run a registered task by name like [`Tasks::run`], retrying it
+    /// following the policy when it fails.
+    ///
+    /// # Errors
+    ///
+    /// When the task is not found, or the error of the last attempt when all
+    /// the attempts failed.
+    pub async fn run_with_retry(
+        &self,
+        app_context: &AppContext<T>,
+        task: &str,
+        vars: &Vars,
+        policy: &RetryPolicy,
+    ) -> Result<()> {
+        let max_attempts = policy.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let (res, _) = self.run_timed(app_context, task, vars).await?;
+            match res {
+                Ok(_) => return Ok(()),
+                Err(err) if attempt >= max_attempts => return Err(err),
+                Err(err) => {
+                    let delay = policy.delay(attempt);
+                    tracing::warn!(
+                        target: TASK_LOG_TARGET,
+                        task,
+                        attempt,
+                        error = %err,
+                        ?delay,
+                        "task failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Run a registered task by name like [`Tasks::run`], holding an
+    /// exclusive lock keyed by the task name, so a single-instance task
+    /// scheduled on several hosts never runs twice at the same time.
+    ///
+    /// # Errors
+    ///
+    /// When the lock is already held, could not be acquired, or when the task
+    /// fails.
+    pub async fn run_exclusive(
+        &self,
+        app_context: &AppContext<T>,
+        task: &str,
+        vars: &Vars,
+        lock: &RunLock,
+    ) -> Result<()> {
+        match lock {
+            RunLock::File(dir) => {
+                let _lock = FileLock::acquire(dir, task)?;
+                self.run(app_context, task, vars).await
+            }
+            #[cfg(feature = "with-db")]
+            RunLock::Db => {
+                use sea_orm::{
+                    sqlx::{self, Connection},
+                    ConnectionTrait, DatabaseBackend, DbErr, RuntimeErr,
+                };
+
+                let db_err = |err| Error::DB(DbErr::Conn(RuntimeErr::SqlxError(err)));
+                if app_context.db.get_database_backend() != DatabaseBackend::Postgres {
+                    return Err(Error::Message(
+                        "database task locks are only supported on postgres".to_string(),
+                    ));
+                }
+                // the session lock lives on a connection of its own, detached
+                // from the pool so it is closed, releasing the lock, even when
+                // the run is cancelled
+                let key = lock_key(task);
+                let mut conn = app_context
+                    .db
+                    .get_postgres_connection_pool()
+                    .acquire()
+                    .await
+                    .map_err(db_err)?
+                    .detach();
+                let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+                    .bind(key)
+                    .fetch_one(&mut conn)
+                    .await
+                    .map_err(db_err)?;
+                if !locked {
+                    return Err(Error::Message(format!(
+                        "task `{task}` is already running: the database lock is held"
+                    )));
+                }
+                let res = self.run(app_context, task, vars).await;
+                let unlocked = sqlx::query_scalar::<_, bool>("SELECT pg_advisory_unlock($1)")
+                    .bind(key)
+                    .fetch_one(&mut conn)
+                    .await;
+                if let Err(err) = unlocked {
+                    tracing::error!(
+                        target: TASK_LOG_TARGET,
+                        task,
+                        error = %err,
+                        "could not release the database task lock, closing its connection"
+                    );
+                }
+                if let Err(err) = conn.close().await {
+                    tracing::debug!(target: TASK_LOG_TARGET, task, error = %err, "could not close the database task lock connection");
+                }
+                res
+            }
+        }
+    }
+}
+
+/// The exclusive lock held by [`Tasks::run_exclusive`] while a task runs.
+#[derive(Debug, Clone)]
+pub enum RunLock {
+    /// A lock file named after the task in the given folder, which must be
+    /// shared by the hosts. The file is removed once the task finished. It
+    /// records the host and process holding it, so a file left behind by a
+    /// crashed process of the same host is taken over; one left behind by
+    /// another host must be removed by hand.
+    File(PathBuf),
+    /// A postgres session advisory lock, held on a dedicated connection
+    /// while the task runs.
+    #[cfg(feature = "with-db")]
+    Db,
+}
+
+/// A lock file, removed when dropped.
+struct FileLock(PathBuf);
+
+impl FileLock {
+    fn acquire(dir: &Path, task: &str) -> Result<Self> {
+        let path = dir.join(format!("{}.lock", task.replace(':', "_")));
+        match Self::create(&path) {
+            Err(Error::IO(err)) if err.kind() == io::ErrorKind::AlreadyExists => {
+                if Self::take_over(&path)? {
+                    Self::create(&path)
+                } else {
+                    Err(Error::Message(format!(
+                        "task `{task}` is already running: {} is held",
+                        path.display()
+                    )))
+                }
+            }
+            res => res,
+        }
+    }
+
+    /// Removes the lock file when it is stale. The file is first moved aside,
+    /// so that of several processes taking over the same stale lock only one
+    /// removes it, and the others fail to create theirs.
+    fn take_over(path: &Path) -> Result<bool> {
+        let Some(holder) = Self::stale_holder(path) else {
+            return Ok(false);
+        };
+        let aside = path.with_extension(format!("lock.{}", std::process::id()));
+        match std::fs::rename(path, &aside) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(true),
+            Err(err) => return Err(err.into()),
+        }
+        let moved = std::fs::read_to_string(&aside)?;
+        if moved != holder {
+            // another process took the lock over meanwhile, give it back
+            let _ = std::fs::hard_link(&aside, path);
+            std::fs::remove_file(&aside)?;
+            return Ok(false);
+        }
+        tracing::warn!(path = %path.display(), holder = holder.trim(), "taking over a stale task lock file");
+        std::fs::remove_file(&aside)?;
+        Ok(true)
+    }
+
+    fn create(path: &Path) -> Result<Self> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        writeln!(file, "{} {}", lock_host(), std::process::id())?;
+        Ok(Self(path.to_path_buf()))
+    }
+
+    /// The content of the lock file when it was left behind by a process of
+    /// this host which is no longer running.
+    fn stale_holder(path: &Path) -> Option<String> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let (host, pid) = content.trim().rsplit_once(' ')?;
+        let stale = host == lock_host() && pid.parse().is_ok_and(|pid| !process_is_running(pid));
+        stale.then_some(content)
+    }
+}
+
+fn lock_host() -> String {
+    hostname::get().map_or_else(
+        |_| "localhost".to_string(),
+        |host| host.to_string_lossy().into_owned(),
+    )
+}
+
+#[cfg(unix)]
+fn process_is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks that the process exists, nothing is sent
+    let res = unsafe { libc::kill(pid, 0) };
+    // EPERM: the process exists, owned by another user
+    res == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a way to check, the holder is assumed to be running.
+#[cfg(not(unix))]
+fn process_is_running(_pid: u32) -> bool {
+    true
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.0) {
+            tracing::warn!(path = %self.0.display(), error = %err, "could not remove task lock file");
+        }
+    }
+}
+
+/// The advisory lock key of a task, a stable FNV-1a hash of its name so all
+/// the hosts agree on it.
+#[cfg(feature = "with-db")]
+fn lock_key(task: &str) -> i64 {
+    let hash = task.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    i64::from_ne_bytes(hash.to_ne_bytes())
+}
+
+/// Unregisters an in-flight run when it completes or is dropped.
+struct RunningGuard<'a> {
+    running: &'a Mutex<BTreeMap<u64, (String, CancellationToken)>>,
+    run_id: u64,
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.run_id);
+    }
+}
+
+/// A built-in task removing the [`TaskHistory`] entries older than a retention
+/// period, registered as `task_history:prune`.
+///
+/// The retention can be overridden per run with the `retention` argument, e.g.
+/// `retention:7d`.
+///
+/// The history lives in the memory of the process, so the task only prunes
+/// when run in-process, e.g. from the [`crate::scheduler::Scheduler`] or the
+/// HTTP task runner. `cargo loco task task_history:prune` starts a new process
+/// with an empty history, and prunes nothing.
+///
+/// ```rust,ignore
+/// fn register_tasks(tasks: &mut Tasks<Self::ExtraAppContext>) {
+///     let prune = PruneTaskHistoryTask::new(tasks.history(), Duration::from_secs(30 * 24 * 60 * 60));
+///     tasks.register(prune);
+/// }
+/// ```
+pub struct PruneTaskHistoryTask {
+    history: TaskHistory,
+    retention: Duration,
+}
+
+impl PruneTaskHistoryTask {
+    #[must_use]
+    pub fn new(history: TaskHistory, retention: Duration) -> Self {
+        Self { history, retention }
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + Clone> Task<T> for PruneTaskHistoryTask {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "task_history:prune".to_string(),
+            detail: "Remove the task run history entries older than the retention period"
+                .to_string(),
+        }
+    }
+
+    fn arg_schema(&self) -> Vec<TaskArg> {
+        vec![TaskArg::optional(
+            "retention",
+            "how long to keep the entries, e.g. 30d",
+        )]
+    }
+
+    async fn run(&self, _app_context: &AppContext<T>, vars: &Vars) -> Result<()> {
+        let retention = if vars.cli.contains_key("retention") {
+            vars.cli_arg_duration("retention")?
+        } else {
+            self.retention
+        };
+        let retention = chrono::Duration::from_std(retention)
+            .map_err(|err| Error::Message(format!("invalid retention {retention:?}: {err}")))?;
+        let cutoff = chrono::Utc::now()
+            .checked_sub_signed(retention)
+            .ok_or_else(|| Error::Message(format!("retention {retention} is too long")))?;
+        let pruned = self.history.prune_before(cutoff);
+        tracing::info!(target: TASK_LOG_TARGET, pruned, "pruned task history");
+        Ok(())
+    }
+}
+
+/// Record the number of arguments of a task run as the `task_arg_count`
+/// histogram.
+fn record_arg_count(task: &str, vars: &Vars) {
+    let count = vars.cli.len();
+    #[allow(clippy::cast_precision_loss)]
+    metrics::histogram!("task_arg_count", "task" => task.to_string()).record(count as f64);
+}
+
+/// Refuse to run a task outside of its allowed environments.
+fn check_environment<T: Send + Sync + Clone>(
+    task: &dyn Task<T>,
+    environment: &Environment,
+) -> Result<()> {
+    let Some(allowed) = task.allowed_environments() else {
+        return Ok(());
+    };
+    if allowed.contains(environment) {
+        return Ok(());
+    }
+    let allowed = allowed
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(Error::Message(format!(
+        "task `{}` is not allowed to run in the `{environment}` environment (allowed: {allowed})",
+        task.task().name
+    )))
+}
+
+/// Escapes a value for a Markdown table cell.
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// A task run request handed to the [`TaskWorker`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskJob {
+    /// The registered task name
+    pub name: String,
+    /// The task arguments
+    pub vars: Vars,
+    /// Unix timestamp (milliseconds) of the moment the job was enqueued
+    pub enqueued_at: i64,
+    /// The correlation id of the task arguments, see
+    /// [`Vars::with_correlation_id`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl TaskJob {
+    /// Creates a new job for the given task, stamped with the current time.
+    #[must_use]
+    pub fn new(name: &str, vars: Vars) -> Self {
+        Self {
+            name: name.to_string(),
+            correlation_id: vars.correlation_id.clone(),
+            vars,
+            enqueued_at: chrono::Utc::now().timestamp_millis(),
+        }
+    }
+
+    /// The size in bytes of the job serialized as the queue payload.
+    ///
+    /// # Errors
+    ///
+    /// When the job could not be serialized
+    pub fn payload_bytes(&self) -> Result<usize> {
+        Ok(serde_json::to_vec(self)?.len())
+    }
+
+    /// Returns how long the job waited in the queue, given the unix timestamp
+    /// (milliseconds) of the moment a worker started it.
+    #[must_use]
+    pub fn queue_wait(&self, started_at: i64) -> Duration {
+        Duration::from_millis(u64::try_from(started_at - self.enqueued_at).unwrap_or_default())
+    }
+}
+
+/// Runs registered tasks in the background worker.
+///
+/// Every job records the time it waited in the queue as the
+/// `task_queue_wait_seconds` histogram, which helps spotting worker
+/// starvation. Enqueuing records the size of the job as the
+/// `task_payload_bytes` histogram, revealing the tasks carrying heavy
+/// payloads.
+pub struct TaskWorker<H: Hooks> {
+    ctx: AppContext<H::ExtraAppContext>,
+    tasks: Arc<Tasks<H::ExtraAppContext>>,
+}
+
+impl<H: Hooks + 'static> TaskWorker<H> {
+    /// A worker running the given registered tasks.
+    pub(crate) fn new(
+        ctx: &AppContext<H::ExtraAppContext>,
+        tasks: Arc<Tasks<H::ExtraAppContext>>,
+    ) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            tasks,
+        }
+    }
+
+    /// Enqueue a registered task to be run by the worker.
+    ///
+    /// # Errors
+    ///
+    /// When the job could not be enqueued
+    pub async fn enqueue(
+        ctx: &AppContext<H::ExtraAppContext>,
+        task: &str,
+        vars: Vars,
+    ) -> Result<()> {
+        let job = TaskJob::new(task, vars);
+        record_payload_size(&job)?;
+        Self::perform_later(ctx, job).await.map_err(Box::from)?;
+        Ok(())
+    }
+}
+
+/// Record the size of an enqueued job as the `task_payload_bytes` histogram.
+fn record_payload_size(job: &TaskJob) -> Result<()> {
+    let bytes = job.payload_bytes()?;
+    #[allow(clippy::cast_precision_loss)]
+    metrics::histogram!("task_payload_bytes", "task" => job.name.clone()).record(bytes as f64);
+    Ok(())
+}
+
+impl<H: Hooks + 'static> AppWorker<TaskJob, H::ExtraAppContext> for TaskWorker<H> {
+    fn build(ctx: &AppContext<H::ExtraAppContext>) -> Self {
+        let mut tasks = Tasks::default();
+        H::register_tasks(&mut tasks);
+        Self::new(ctx, Arc::new(tasks))
+    }
+}
+
+#[async_trait]
+impl<H: Hooks + 'static> Worker<TaskJob> for TaskWorker<H> {
+    fn class_name() -> String {
+        "TaskWorker".to_string()
+    }
+
+    async fn perform(&self, job: TaskJob) -> sidekiq::Result<()> {
+        let wait = job.queue_wait(chrono::Utc::now().timestamp_millis());
+        metrics::histogram!("task_queue_wait_seconds", "task" => job.name.clone())
+            .record(wait.as_secs_f64());
+
+        tracing::debug!(task = %job.name, wait = ?wait, "task picked up from queue");
+        let vars = Vars {
+            correlation_id: job.correlation_id,
+            ..job.vars
+        };
+        Ok(self
+            .tasks
+            .run(&self.ctx, &job.name, &vars)
+            .await
+            .map_err(Box::from)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+
+    use super::*;
+    use crate::{
+        tests_cfg,
+        tests_cfg::{capture::Capture, metrics::MetricsCapture},
+    };
+
+    /// A task recording its runs in a shared list.
+    struct RecordTask {
+        name: &'static str,
+        runs: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Task<()> for RecordTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: self.name.to_string(),
+                detail: format!("records {}", self.name),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            self.runs.lock().unwrap().push(self.name.to_string());
+            Ok(())
+        }
+    }
+
+    /// A task emitting a log line.
+    struct LogTask;
+
+    #[async_trait]
+    impl Task<()> for LogTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "log".to_string(),
+                detail: "logs a line".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            tracing::info!("running log task");
+            Ok(())
+        }
+    }
+
+    /// A task always failing.
+    struct FailTask;
+
+    #[async_trait]
+    impl Task<()> for FailTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "fail".to_string(),
+                detail: "always fails".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            Err(Error::Message("boom".to_string()))
+        }
+    }
+
+    /// A task returning a report after a short while.
+    struct ReportTask;
+
+    #[async_trait]
+    impl Task<()> for ReportTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "report".to_string(),
+                detail: "reports users".to_string(),
+            }
+        }
+
+        fn output_schema(&self) -> Option<serde_json::Value> {
+            Some(serde_json::json!({
+                "type": "object",
+                "properties": { "users": { "type": "integer" } },
+            }))
+        }
+
+        async fn run(&self, app_context: &AppContext<()>, vars: &Vars) -> Result<()> {
+            self.run_with_output(app_context, vars).await.map(|_| ())
+        }
+
+        async fn run_with_output(
+            &self,
+            _app_context: &AppContext<()>,
+            _vars: &Vars,
+        ) -> Result<Option<serde_json::Value>> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(Some(serde_json::json!({ "users": 3 })))
+        }
+    }
+
+    /// A task printing to its standard output and error.
+    struct PrintTask;
+
+    #[async_trait]
+    impl Task<()> for PrintTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "print".to_string(),
+                detail: "prints lines".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, vars: &Vars) -> Result<()> {
+            writeln!(stdout(), "hello {}", vars.cli_arg("name")?)?;
+            write!(stdout(), "done")?;
+            writeln!(stderr(), "a warning")?;
+            Ok(())
+        }
+    }
+
+    /// A task declaring its arguments.
+    struct SeedTask;
+
+    #[async_trait]
+    impl Task<()> for SeedTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "db:seed".to_string(),
+                detail: "Seed the database | from fixtures".to_string(),
+            }
+        }
+
+        fn arg_schema(&self) -> Vec<TaskArg> {
+            vec![
+                TaskArg::required("path", "The fixtures folder"),
+                TaskArg::optional("reset", "Truncate the tables first"),
+            ]
+        }
+
+        fn estimated_duration(&self) -> Option<Duration> {
+            Some(Duration::from_secs(2 * 60 * 60))
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A task sleeping for a while.
+    struct SleepTask(Duration);
+
+    #[async_trait]
+    impl Task<()> for SleepTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "sleep".to_string(),
+                detail: "sleeps".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            tokio::time::sleep(self.0).await;
+            Ok(())
+        }
+    }
+
+    /// A task emitting events while it runs.
+    struct EventTask;
+
+    #[async_trait]
+    impl Task<()> for EventTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "events".to_string(),
+                detail: "emits events".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            emit(TaskEvent::Phase {
+                name: "import".to_string(),
+            });
+            for current in 1..=2 {
+                emit(TaskEvent::Progress {
+                    current,
+                    total: Some(2),
+                });
+            }
+            emit(TaskEvent::Log {
+                message: "imported 2 users".to_string(),
+            });
+            Ok(())
+        }
+    }
+
+    /// A task only allowed in development.
+    struct SeedFakeDataTask;
+
+    #[async_trait]
+    impl Task<()> for SeedFakeDataTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "seed_fake_data".to_string(),
+                detail: "loads fake data".to_string(),
+            }
+        }
+
+        fn allowed_environments(&self) -> Option<Vec<Environment>> {
+            Some(vec![Environment::Development])
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A task recording its `on_success` invocations, failing on demand.
+    struct NotifyTask {
+        notified: Arc<Mutex<Vec<Option<serde_json::Value>>>>,
+    }
+
+    #[async_trait]
+    impl Task<()> for NotifyTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "reimport".to_string(),
+                detail: "re-imports users".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, vars: &Vars) -> Result<()> {
+            if vars.cli.contains_key("fail") {
+                return Err(Error::Message("import failed".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn on_success(
+            &self,
+            _app_context: &AppContext<()>,
+            output: Option<&serde_json::Value>,
+        ) -> Result<()> {
+            self.notified.lock().unwrap().push(output.cloned());
+            Ok(())
+        }
+    }
+
+    /// A task failing its first `failures` runs.
+    struct FlakyTask {
+        runs: Arc<AtomicUsize>,
+        failures: usize,
+    }
+
+    #[async_trait]
+    impl Task<()> for FlakyTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "flaky".to_string(),
+                detail: "fails a few times".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            if self.runs.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(Error::Message("flaked".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    fn record_tasks(names: &[&'static str]) -> (Tasks<()>, Arc<Mutex<Vec<String>>>) {
+        let runs = Arc::new(Mutex::new(vec![]));
+        let mut tasks = Tasks::default();
+        for name in names {
+            tasks.register(RecordTask {
+                name,
+                runs: runs.clone(),
+            });
+        }
+        (tasks, runs)
+    }
+
+    fn batch(names: &[&str]) -> Vec<BatchTask> {
+        names
+            .iter()
+            .map(|name| BatchTask::new(name, Vars::default()))
+            .collect()
+    }
+
+    #[test]
+    fn can_render_markdown_catalog() {
+        let (mut tasks, _) = record_tasks(&["cleanup"]);
+        tasks.register(SeedTask);
+
+        assert_eq!(
+            tasks.to_markdown(),
+            "| Task | Namespace | Detail | Arguments |
+| --- | --- | --- | --- |
+| `cleanup` |  | records cleanup |  |
+| `db:seed` | `db` | Seed the database \\| from fixtures | `path` (required): The fixtures \
+             folder<br>`reset` (optional): Truncate the tables first |
+"
+        );
+    }
+
+    #[test]
+    fn can_generate_usage_example() {
+        let (mut tasks, _) = record_tasks(&["cleanup"]);
+        tasks.register(SeedTask);
+
+        assert_eq!(
+            tasks.usage_example("db:seed").unwrap(),
+            "cargo loco task db:seed path:<path> [reset:<reset>]"
+        );
+        assert_eq!(
+            tasks.usage_example("cleanup").unwrap(),
+            "cargo loco task cleanup"
+        );
+        assert!(matches!(
+            tasks.usage_example("missing"),
+            Err(Error::TaskNotFound(name)) if name == "missing"
+        ));
+    }
+
+    fn names(batch: &[BatchTask]) -> Vec<&str> {
+        batch.iter().map(|task| task.name.as_str()).collect()
+    }
+
+    #[test]
+    fn can_batch_a_namespace() {
+        let (tasks, _) = record_tasks(&["db:vacuum", "cleanup", "db:reindex", "db:analyze"]);
+
+        assert_eq!(
+            names(&tasks.namespace_batch("db")),
+            vec!["db:analyze", "db:reindex", "db:vacuum"]
+        );
+        assert!(tasks.namespace_batch("cache").is_empty());
+    }
+
+    #[test]
+    fn seeded_sample_batch_is_deterministic() {
+        let (tasks, _) = record_tasks(&[
+            "db:a", "db:b", "db:c", "db:d", "db:e", "db:f", "db:g", "other",
+        ]);
+
+        let first = tasks.sample_batch("db", 3, Some(42));
+        let second = tasks.sample_batch("db", 3, Some(42));
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(names(&first), names(&second));
+        assert!(names(&first).iter().all(|name| name.starts_with("db:")));
+        let mut unique = names(&first);
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), 3);
+    }
+
+    #[test]
+    fn sample_batch_is_capped_to_the_namespace() {
+        let (tasks, _) = record_tasks(&["db:a", "db:b", "other"]);
+
+        let mut sample = names(&tasks.sample_batch("db", 10, Some(1)))
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        sample.sort();
+
+        assert_eq!(sample, vec!["db:a", "db:b"]);
+    }
+
+    #[tokio::test]
+    async fn can_run_a_sample_batch() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (tasks, runs) = record_tasks(&["db:a", "db:b", "db:c", "db:d"]);
+
+        let batch = tasks.sample_batch("db", 2, Some(7));
+        tasks.run_many(&ctx, &batch).await.unwrap();
+
+        assert_eq!(*runs.lock().unwrap(), names(&batch));
+    }
+
+    #[test]
+    fn can_list_catalog_as_json() {
+        let (mut tasks, _) = record_tasks(&["cleanup"]);
+        tasks.register(SeedTask);
+        tasks.register(ReportTask);
+
+        assert_eq!(
+            tasks.list_json(),
+            serde_json::json!([
+                {
+                    "name": "cleanup",
+                    "namespace": null,
+                    "detail": "records cleanup",
+                    "args": [],
+                    "output_schema": null,
+                    "estimated_duration_secs": null,
+                },
+                {
+                    "name": "db:seed",
+                    "namespace": "db",
+                    "detail": "Seed the database | from fixtures",
+                    "args": [
+                        { "name": "path", "description": "The fixtures folder", "required": true },
+                        { "name": "reset", "description": "Truncate the tables first", "required": false },
+                    ],
+                    "output_schema": null,
+                    "estimated_duration_secs": 7200,
+                },
+                {
+                    "name": "report",
+                    "namespace": null,
+                    "detail": "reports users",
+                    "args": [],
+                    "output_schema": {
+                        "type": "object",
+                        "properties": { "users": { "type": "integer" } },
+                    },
+                    "estimated_duration_secs": null,
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn can_run_many() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (tasks, runs) = record_tasks(&["a", "b"]);
+
+        tasks.run_many(&ctx, &batch(&["b", "a"])).await.unwrap();
+
+        assert_eq!(*runs.lock().unwrap(), vec!["b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn can_run_many_within_deadline() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (tasks, runs) = record_tasks(&["a", "b"]);
+
+        tasks
+            .run_many_with_timeout(&ctx, &batch(&["a", "b"]), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(*runs.lock().unwrap(), vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn batch_exceeding_deadline_reports_completed_tasks() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (mut tasks, runs) = record_tasks(&["a", "b"]);
+        tasks.register(SleepTask(Duration::from_secs(10)));
+
+        let res = tasks
+            .run_many_with_timeout(
+                &ctx,
+                &batch(&["a", "sleep", "b"]),
+                Duration::from_millis(50),
+            )
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(Error::Message(msg)) if msg == "task batch timed out after 50ms, completed tasks: a"
+        ));
+        assert_eq!(*runs.lock().unwrap(), vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn failing_guard_aborts_batch() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (mut tasks, runs) = record_tasks(&["a", "b"]);
+        let checks = Arc::new(AtomicUsize::new(0));
+        let guard_checks = checks.clone();
+        tasks.set_guard(move |_ctx: &AppContext<()>| {
+            guard_checks.fetch_add(1, Ordering::SeqCst);
+            Err(Error::Message("not the expected database".to_string()))
+        });
+
+        let res = tasks.run_many(&ctx, &batch(&["a", "b"])).await;
+
+        assert!(matches!(res, Err(Error::Message(msg)) if msg == "not the expected database"));
+        assert_eq!(checks.load(Ordering::SeqCst), 1);
+        assert!(runs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn subscriber_receives_events_in_order() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(EventTask);
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        let result = tasks
+            .run_with_events(&ctx, "events", &Vars::default(), sender)
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        let mut events = vec![];
+        while let Some(event) = receiver.recv().await {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                TaskEvent::Phase {
+                    name: "import".to_string()
+                },
+                TaskEvent::Progress {
+                    current: 1,
+                    total: Some(2)
+                },
+                TaskEvent::Progress {
+                    current: 2,
+                    total: Some(2)
+                },
+                TaskEvent::Log {
+                    message: "imported 2 users".to_string()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn emit_without_subscriber_is_a_noop() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(EventTask);
+
+        tasks.run(&ctx, "events", &Vars::default()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dev_only_task_is_refused_in_production() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.environment = Environment::Production;
+        let mut tasks = Tasks::default();
+        tasks.register(SeedFakeDataTask);
+
+        let res = tasks.run(&ctx, "seed_fake_data", &Vars::default()).await;
+
+        assert!(matches!(
+            res,
+            Err(Error::Message(msg)) if msg == "task `seed_fake_data` is not allowed to run in the `production` environment (allowed: development)"
+        ));
+    }
+
+    #[tokio::test]
+    async fn dev_only_task_runs_in_development() {
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.environment = Environment::Development;
+        let mut tasks = Tasks::default();
+        tasks.register(SeedFakeDataTask);
+
+        tasks
+            .run(&ctx, "seed_fake_data", &Vars::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn on_success_runs_only_on_success() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let notified = Arc::new(Mutex::new(vec![]));
+        let mut tasks = Tasks::default();
+        tasks.register(NotifyTask {
+            notified: notified.clone(),
+        });
+
+        let failing = Vars::from_cli_args(vec![("fail".to_string(), "true".to_string())]);
+        assert!(tasks.run(&ctx, "reimport", &failing).await.is_err());
+        assert!(notified.lock().unwrap().is_empty());
+
+        tasks.run(&ctx, "reimport", &Vars::default()).await.unwrap();
+        assert_eq!(*notified.lock().unwrap(), vec![None]);
+    }
+
+    #[tokio::test]
+    async fn running_tasks_updates_stats_snapshot() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (mut tasks, _) = record_tasks(&["a", "b"]);
+        tasks.register(FailTask);
+        assert!(tasks.metrics_snapshot().is_empty());
+
+        let before = chrono::Utc::now();
+        tasks.run(&ctx, "a", &Vars::default()).await.unwrap();
+        tasks.run(&ctx, "a", &Vars::default()).await.unwrap();
+        assert!(tasks.run(&ctx, "fail", &Vars::default()).await.is_err());
+
+        let snapshot = tasks.metrics_snapshot();
+        assert_eq!(snapshot.keys().collect::<Vec<_>>(), vec!["a", "fail"]);
+        assert_eq!(snapshot["a"].runs, 2);
+        assert_eq!(snapshot["a"].failures, 0);
+        assert!(snapshot["a"].last_run_at.is_some_and(|at| at >= before));
+        assert!(snapshot["a"].last_duration.is_some());
+        assert_eq!(snapshot["fail"].runs, 1);
+        assert_eq!(snapshot["fail"].failures, 1);
+    }
+
+    fn history_record(task: &str, age: chrono::Duration) -> TaskRunRecord {
+        TaskRunRecord {
+            task: task.to_string(),
+            finished_at: chrono::Utc::now() - age,
+            duration: Duration::from_millis(10),
+            success: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn prune_task_history_removes_entries_older_than_retention() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (mut tasks, _) = record_tasks(&["a"]);
+        let history = tasks.history();
+        history.record(history_record("old", chrono::Duration::days(40)));
+        history.record(history_record("recent", chrono::Duration::days(2)));
+        tasks.register(PruneTaskHistoryTask::new(
+            tasks.history(),
+            Duration::from_secs(30 * 24 * 60 * 60),
+        ));
+        tasks.run(&ctx, "a", &Vars::default()).await.unwrap();
+
+        tasks
+            .run(&ctx, "task_history:prune", &Vars::default())
+            .await
+            .unwrap();
+
+        let names = history
+            .entries()
+            .into_iter()
+            .map(|entry| entry.task)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["recent", "a", "task_history:prune"]);
+    }
+
+    #[test]
+    fn task_history_keeps_the_last_runs() {
+        let history = TaskHistory::default();
+        history.set_max_len(2);
+        for name in ["a", "b", "c"] {
+            history.record(history_record(name, chrono::Duration::minutes(1)));
+        }
+
+        let names = history
+            .entries()
+            .into_iter()
+            .map(|entry| entry.task)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["b", "c"]);
+
+        history.set_max_len(0);
+        history.record(history_record("d", chrono::Duration::minutes(1)));
+        assert!(history.entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn prune_task_history_retention_can_be_overridden() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        let history = tasks.history();
+        history.record(history_record("old", chrono::Duration::days(2)));
+        history.record(history_record("recent", chrono::Duration::minutes(5)));
+        tasks.register(PruneTaskHistoryTask::new(
+            tasks.history(),
+            Duration::from_secs(30 * 24 * 60 * 60),
+        ));
+
+        let vars = Vars::from_cli_args(vec![("retention".to_string(), "1d".to_string())]);
+        tasks.run(&ctx, "task_history:prune", &vars).await.unwrap();
+
+        let names = history
+            .entries()
+            .into_iter()
+            .map(|entry| entry.task)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["recent", "task_history:prune"]);
+    }
+
+    #[tokio::test]
+    async fn prune_task_history_rejects_a_too_long_retention() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(PruneTaskHistoryTask::new(
+            tasks.history(),
+            Duration::from_secs(60),
+        ));
+
+        let vars = Vars::from_cli_args(vec![("retention".to_string(), "100000000d".to_string())]);
+        let res = tasks.run(&ctx, "task_history:prune", &vars).await;
+        assert!(matches!(res, Err(Error::Message(msg)) if msg.contains("is too long")));
+    }
+
+    #[tokio::test]
+    async fn exclusive_run_fails_while_the_lock_is_held() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (tasks, runs) = record_tasks(&["db:cleanup"]);
+        let dir = std::env::temp_dir().join(format!("loco-task-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lock = RunLock::File(dir.clone());
+
+        // another process holds the lock
+        let held = FileLock::acquire(&dir, "db:cleanup").unwrap();
+        let res = tasks
+            .run_exclusive(&ctx, "db:cleanup", &Vars::default(), &lock)
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::Message(msg)) if msg.starts_with("task `db:cleanup` is already running")
+        ));
+        assert!(runs.lock().unwrap().is_empty());
+
+        drop(held);
+        tasks
+            .run_exclusive(&ctx, "db:cleanup", &Vars::default(), &lock)
+            .await
+            .unwrap();
+        assert_eq!(*runs.lock().unwrap(), vec!["db:cleanup"]);
+        assert!(!dir.join("db_cleanup.lock").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Set to the lock folder when the test binary runs as the second
+    /// process of [`second_process_cannot_take_the_lock`].
+    const LOCK_DIR_ENV: &str = "LOCO_TEST_TASK_LOCK_DIR";
+
+    #[test]
+    fn second_process_cannot_take_the_lock() {
+        let dir = std::env::temp_dir().join(format!("loco-task-lock-proc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let held = FileLock::acquire(&dir, "db:cleanup").unwrap();
+
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "task::tests::lock_is_held_by_another_process",
+                "--test-threads=1",
+            ])
+            .env(LOCK_DIR_ENV, &dir)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        drop(held);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Runs in the second process of [`second_process_cannot_take_the_lock`].
+    #[test]
+    fn lock_is_held_by_another_process() {
+        let Some(dir) = std::env::var_os(LOCK_DIR_ENV) else {
+            return;
+        };
+        let res = FileLock::acquire(Path::new(&dir), "db:cleanup");
+        assert!(matches!(
+            res,
+            Err(Error::Message(msg)) if msg.starts_with("task `db:cleanup` is already running")
+        ));
+    }
+
+    #[tokio::test]
+    async fn exclusive_run_takes_over_a_stale_lock() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (tasks, runs) = record_tasks(&["db:cleanup"]);
+        let dir = std::env::temp_dir().join(format!("loco-task-lock-stale-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lock = RunLock::File(dir.clone());
+
+        // left behind by a process which exited
+        let mut exited = std::process::Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        exited.wait().unwrap();
+        std::fs::write(
+            dir.join("db_cleanup.lock"),
+            format!("{} {}\n", lock_host(), exited.id()),
+        )
+        .unwrap();
+
+        tasks
+            .run_exclusive(&ctx, "db:cleanup", &Vars::default(), &lock)
+            .await
+            .unwrap();
+        assert_eq!(*runs.lock().unwrap(), vec!["db:cleanup"]);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lock_of_another_host_is_not_stale() {
+        let dir = std::env::temp_dir().join(format!("loco-task-lock-host-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("db_cleanup.lock"), "another-host 1\n").unwrap();
+
+        assert!(FileLock::acquire(&dir, "db:cleanup").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "with-db")]
+    #[tokio::test]
+    async fn database_lock_requires_postgres() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (tasks, runs) = record_tasks(&["db:cleanup"]);
+
+        let res = tasks
+            .run_exclusive(&ctx, "db:cleanup", &Vars::default(), &RunLock::Db)
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::Message(msg)) if msg == "database task locks are only supported on postgres"
+        ));
+        assert!(runs.lock().unwrap().is_empty());
+    }
+
+    /// Runs against the postgres database of `DATABASE_URL`, when set.
+    #[cfg(feature = "with-db")]
+    #[tokio::test]
+    async fn exclusive_run_fails_while_the_database_lock_is_held() {
+        use sea_orm::{ConnectionTrait, DatabaseBackend, Statement, TransactionTrait};
+
+        let Ok(uri) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let mut ctx = tests_cfg::app::get_app_context().await;
+        ctx.db = sea_orm::Database::connect(&uri).await.unwrap();
+        let (tasks, runs) = record_tasks(&["db:cleanup"]);
+
+        // another host holds the lock
+        let other = sea_orm::Database::connect(&uri).await.unwrap();
+        let held = other.begin().await.unwrap();
+        held.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            "SELECT pg_advisory_xact_lock($1)",
+            [lock_key("db:cleanup").into()],
+        ))
+        .await
+        .unwrap();
+        let res = tasks
+            .run_exclusive(&ctx, "db:cleanup", &Vars::default(), &RunLock::Db)
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::Message(msg)) if msg.starts_with("task `db:cleanup` is already running")
+        ));
+        assert!(runs.lock().unwrap().is_empty());
+
+        held.rollback().await.unwrap();
+        tasks
+            .run_exclusive(&ctx, "db:cleanup", &Vars::default(), &RunLock::Db)
+            .await
+            .unwrap();
+        assert_eq!(*runs.lock().unwrap(), vec!["db:cleanup"]);
+
+        // the lock is released once the task ran
+        let released = other
+            .query_one(Statement::from_sql_and_values(
+                DatabaseBackend::Postgres,
+                "SELECT pg_try_advisory_xact_lock($1) AS locked",
+                [lock_key("db:cleanup").into()],
+            ))
+            .await
+            .unwrap()
+            .unwrap()
+            .try_get::<bool>("", "locked")
+            .unwrap();
+        assert!(released);
+    }
+
+    #[test]
+    fn jitter_keeps_delays_within_bounds() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: 0.5,
+            backoff_strategy: BackoffStrategy::ExponentialWithJitter,
+        };
+
+        for _ in 0..100 {
+            let delay = policy.delay(3);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+            let capped = policy.delay(10);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn non_finite_jitter_is_ignored() {
+        for jitter in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let policy = RetryPolicy {
+                base_delay: Duration::from_millis(100),
+                jitter,
+                backoff_strategy: BackoffStrategy::ExponentialWithJitter,
+                ..RetryPolicy::default()
+            };
+            assert_eq!(policy.delay(2), Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn backoff_strategies_compute_delays() {
+        let fixed = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            backoff_strategy: BackoffStrategy::Fixed,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(fixed.delay(1), Duration::from_millis(100));
+        assert_eq!(fixed.delay(4), Duration::from_millis(100));
+
+        let exponential = RetryPolicy {
+            backoff_strategy: BackoffStrategy::Exponential,
+            ..fixed
+        };
+        assert_eq!(exponential.delay(1), Duration::from_millis(100));
+        assert_eq!(exponential.delay(4), Duration::from_millis(800));
+        assert_eq!(exponential.delay(100), Duration::from_secs(30));
+    }
+
+    fn instant_retries(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::ZERO,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_attempts_are_capped() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Tasks::default();
+        tasks.register(FlakyTask {
+            runs: runs.clone(),
+            failures: usize::MAX,
+        });
+
+        let res = tasks
+            .run_with_retry(&ctx, "flaky", &Vars::default(), &instant_retries(3))
+            .await;
+
+        assert!(matches!(res, Err(Error::Message(msg)) if msg == "flaked"));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_stops_once_the_task_succeeds() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Tasks::default();
+        tasks.register(FlakyTask {
+            runs: runs.clone(),
+            failures: 1,
+        });
+
+        tasks
+            .run_with_retry(&ctx, "flaky", &Vars::default(), &instant_retries(3))
+            .await
+            .unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn can_run_detailed() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(ReportTask);
+
+        let result = tasks
+            .run_detailed(&ctx, "report", &Vars::default())
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(result.name, "report");
+        assert!(result.duration >= Duration::from_millis(50));
+        assert!(result.duration < Duration::from_secs(5));
+        assert_eq!(result.output, Some(serde_json::json!({ "users": 3 })));
+    }
+
+    #[tokio::test]
+    async fn run_detailed_reports_failures() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(FailTask);
+        tasks.register(LogTask);
+
+        let failed = tasks
+            .run_detailed(&ctx, "fail", &Vars::default())
+            .await
+            .unwrap();
+        assert_eq!(failed.status, TaskStatus::Failed("boom".to_string()));
+        assert_eq!(failed.output, None);
+
+        let succeeded = tasks
+            .run_detailed(&ctx, "log", &Vars::default())
+            .await
+            .unwrap();
+        assert_eq!(succeeded.status, TaskStatus::Succeeded);
+        assert_eq!(succeeded.output, None);
+
+        assert!(tasks
+            .run_detailed(&ctx, "missing", &Vars::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn can_capture_task_output() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(PrintTask);
+
+        let vars = Vars::from_cli_args(vec![("name".to_string(), "loco".to_string())]);
+        let (result, output) = tasks.run_captured(&ctx, "print", &vars).await.unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(
+            output,
+            CapturedOutput {
+                stdout: "hello loco\ndone".to_string(),
+                stderr: "a warning\n".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn can_capture_failed_task_output() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(PrintTask);
+
+        let (result, output) = tasks
+            .run_captured(&ctx, "print", &Vars::default())
+            .await
+            .unwrap();
+
+        assert!(!result.is_success());
+        assert_eq!(output, CapturedOutput::default());
+    }
+
+    #[tokio::test]
+    async fn can_log_tasks_under_the_task_target() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(LogTask);
+
+        let capture = Capture::default();
+        {
+            let _guard = capture.set_default();
+            tasks.run(&ctx, "log", &Vars::default()).await.unwrap();
+        }
+
+        let started = capture.events_with_message("task started");
+        assert_eq!(started.len(), 1);
+        assert_eq!(started[0].target, TASK_LOG_TARGET);
+        let finished = capture.events_with_message("task finished");
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].target, TASK_LOG_TARGET);
+
+        let events = capture.events_with_message("running log task");
+        assert_eq!(events[0].spans[0].name, "task");
+        assert_eq!(events[0].spans[0].target, TASK_LOG_TARGET);
+    }
+
+    #[tokio::test]
+    async fn can_log_task_failures_under_the_task_target() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(FailTask);
+
+        let capture = Capture::default();
+        {
+            let _guard = capture.set_default();
+            assert!(tasks.run(&ctx, "fail", &Vars::default()).await.is_err());
+        }
+
+        let failed = capture.events_with_message("task failed");
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].target, TASK_LOG_TARGET);
+        assert_eq!(failed[0].level, tracing::Level::ERROR);
+        assert_eq!(failed[0].span_field("task"), Some("fail"));
+    }
+
+    /// A task declaring its side effects, counting its runs.
+    struct PurgeTask(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Task<()> for PurgeTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "sessions:purge".to_string(),
+                detail: "purges expired sessions".to_string(),
+            }
+        }
+
+        fn describe_effects(&self, vars: &Vars) -> Vec<String> {
+            let days = vars.cli_arg("days").map_or("30", String::as_str);
+            vec![
+                format!("delete sessions expired more than {days} days ago"),
+                "vacuum the sessions table".to_string(),
+            ]
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_logs_effects_without_running() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Tasks::default();
+        tasks.register(PurgeTask(runs.clone()));
+        tasks.set_dry_run(true);
+
+        let capture = Capture::default();
+        {
+            let _guard = capture.set_default();
+            let vars = Vars::from_cli_args(vec![("days".to_string(), "7".to_string())]);
+            tasks.run(&ctx, "sessions:purge", &vars).await.unwrap();
+        }
+
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        let effects = capture
+            .events_with_message("planned effect")
+            .into_iter()
+            .map(|event| event.field("effect").unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            effects,
+            vec![
+                "delete sessions expired more than 7 days ago",
+                "vacuum the sessions table"
+            ]
+        );
+        assert!(capture.events_with_message("task started").is_empty());
+        assert!(tasks.history().entries().is_empty());
+
+        tasks.set_dry_run(false);
+        tasks
+            .run(&ctx, "sessions:purge", &Vars::default())
+            .await
+            .unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    /// A long-running task stopping when cancelled.
+    struct CooperativeTask(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Task<()> for CooperativeTask {
+        fn task(&self) -> TaskInfo {
+            TaskInfo {
+                name: "reindex".to_string(),
+                detail: "reindexes in batches".to_string(),
+            }
+        }
+
+        async fn run(&self, _app_context: &AppContext<()>, _vars: &Vars) -> Result<()> {
+            let token = cancellation_token();
+            for _ in 0..1000 {
+                if token.is_cancelled() {
+                    return Ok(());
+                }
+                self.0.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            Err(Error::Message("ran to completion".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn cooperative_task_stops_when_cancelled() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let batches = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Tasks::default();
+        tasks.register(CooperativeTask(batches.clone()));
+        assert!(!tasks.cancel("reindex"));
+
+        let vars = Vars::default();
+        let started = Instant::now();
+        let (res, ()) = tokio::join!(tasks.run(&ctx, "reindex", &vars), async {
+            while batches.load(Ordering::SeqCst) < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            assert!(tasks.cancel("reindex"));
+        });
+
+        res.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!tasks.cancel("reindex"));
+    }
+
+    #[tokio::test]
+    async fn can_attach_correlation_id_to_task_span() {
+        let capture = Capture::default();
+        let _guard = capture.set_default();
+        let ctx = tests_cfg::app::get_app_context().await;
+        let mut tasks = Tasks::default();
+        tasks.register(LogTask);
+
+        tasks
+            .run(
+                &ctx,
+                "log",
+                &Vars::default().with_correlation_id("request-1234"),
+            )
+            .await
+            .unwrap();
+
+        let events = capture.events_with_message("running log task");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].span_field("task"), Some("log"));
+        assert_eq!(events[0].span_field("correlation_id"), Some("request-1234"));
+    }
+
+    #[tokio::test]
+    async fn passing_guard_runs_batch() {
+        let ctx = tests_cfg::app::get_app_context().await;
+        let (mut tasks, runs) = record_tasks(&["a"]);
+        tasks.set_guard(|_ctx: &AppContext<()>| Ok(()));
+
+        tasks.run_many(&ctx, &batch(&["a"])).await.unwrap();
+
+        assert_eq!(*runs.lock().unwrap(), vec!["a"]);
+    }
+
+    #[test]
+    fn records_task_arg_count_and_payload_size() {
+        let capture = MetricsCapture::default();
+        let vars = Vars::from_cli_args(vec![
+            ("path".to_string(), "seeds".to_string()),
+            ("reset".to_string(), "true".to_string()),
+        ])
+        .with_correlation_id("req-1");
+        let job = TaskJob::new("db:seed", vars.clone());
+
+        metrics::with_local_recorder(&capture, || {
+            record_arg_count("db:seed", &vars);
+            record_payload_size(&job).unwrap();
+        });
+
+        assert_eq!(capture.values("task_arg_count"), vec![2.0]);
+        #[allow(clippy::cast_precision_loss)]
+        let size = serde_json::to_vec(&job).unwrap().len() as f64;
+        assert_eq!(capture.values("task_payload_bytes"), vec![size]);
+        let recorded = capture.recorded();
+        assert!(recorded
+            .iter()
+            .all(|value| value.labels["task"] == "db:seed"));
+    }
+
+    #[test]
+    fn correlation_id_is_not_an_argument() {
+        let vars = Vars::from_cli_args(vec![("path".to_string(), "seeds".to_string())])
+            .with_correlation_id("req-1");
+
+        assert_eq!(vars.cli.len(), 1);
+        assert!(vars.cli_arg("correlation_id").is_err());
+
+        // callers cannot set it through the arguments they send
+        let sent: Vars =
+            serde_json::from_value(serde_json::json!({ "cli": {}, "correlation_id": "spoofed" }))
+                .unwrap();
+        assert_eq!(sent.correlation_id(), None);
+        assert_eq!(
+            serde_json::to_value(&vars).unwrap()["correlation_id"],
+            serde_json::Value::Null
+        );
+
+        // it survives the queue
+        let job: TaskJob =
+            serde_json::from_slice(&serde_json::to_vec(&TaskJob::new("db:seed", vars)).unwrap())
+                .unwrap();
+        assert_eq!(job.correlation_id.as_deref(), Some("req-1"));
+    }
+
+    #[test]
+    fn can_compute_queue_wait() {
+        let job = TaskJob {
+            name: "foo".to_string(),
+            vars: Vars::default(),
+            enqueued_at: 1_000,
+            correlation_id: None,
+        };
+
+        assert_eq!(job.queue_wait(3_500), Duration::from_millis(2_500));
+        assert_eq!(job.queue_wait(1_000), Duration::ZERO);
+    }
+
+    #[test]
+    fn queue_wait_is_zero_on_clock_skew() {
+        let job = TaskJob {
+            name: "foo".to_string(),
+            vars: Vars::default(),
+            enqueued_at: 5_000,
+            correlation_id: None,
+        };
+
+        assert_eq!(job.queue_wait(4_000), Duration::ZERO);
+    }
+
+    #[test]
+    fn new_job_is_stamped_with_enqueue_time() {
+        let before = chrono::Utc::now().timestamp_millis();
+        let job = TaskJob::new("foo", Vars::default());
+        let after = chrono::Utc::now().timestamp_millis();
+
+        assert!(job.enqueued_at >= before && job.enqueued_at <= after);
+        assert!(
+            job.queue_wait(after) <= Duration::from_millis(u64::try_from(after - before).unwrap())
+        );
+    }
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn can_parse_argv_forms() {
+        let vars = Vars::parse_argv(&argv(&["--user", "1", "--format=csv", "since=2024"])).unwrap();
+
+        assert_eq!(vars.cli_arg("user").unwrap(), "1");
+        assert_eq!(vars.cli_arg("format").unwrap(), "csv");
+        assert_eq!(vars.cli_arg("since").unwrap(), "2024");
+        assert!(vars.positional.is_empty());
+    }
+
+    #[test]
+    fn can_parse_argv_flags() {
+        let vars = Vars::parse_argv(&argv(&["--dry-run", "--user", "1", "--verbose"])).unwrap();
+
+        assert_eq!(vars.cli_arg("dry-run").unwrap(), "true");
+        assert_eq!(vars.cli_arg("user").unwrap(), "1");
+        assert_eq!(vars.cli_arg("verbose").unwrap(), "true");
+    }
+
+    #[test]
+    fn can_parse_argv_positionals() {
+        let vars = Vars::parse_argv(&argv(&[
+            "input.csv",
+            "--user=1",
+            "output.csv",
+            "--",
+            "--not-an-option",
+            "a=b",
+        ]))
+        .unwrap();
+
+        assert_eq!(vars.cli_arg("user").unwrap(), "1");
+        assert_eq!(
+            vars.positional,
+            vec!["input.csv", "output.csv", "--not-an-option", "a=b"]
+        );
+        assert!(vars.cli_arg("a").is_err());
+    }
+
+    #[test]
+    fn can_parse_argv_values_with_equal_signs() {
+        let vars = Vars::parse_argv(&argv(&["--filter=a=b", "query=x=1"])).unwrap();
+
+        assert_eq!(vars.cli_arg("filter").unwrap(), "a=b");
+        assert_eq!(vars.cli_arg("query").unwrap(), "x=1");
+    }
+
+    fn vars(key: &str, value: &str) -> Vars {
+        Vars::from_cli_args(vec![(key.to_string(), value.to_string())])
+    }
+
+    #[test]
+    fn can_parse_duration_args() {
+        for (value, expected) in [
+            ("250ms", Duration::from_millis(250)),
+            ("30s", Duration::from_secs(30)),
+            ("5m", Duration::from_secs(300)),
+            ("1h", Duration::from_secs(3600)),
+            ("2d", Duration::from_secs(172_800)),
+            ("1h30m", Duration::from_secs(5400)),
+            ("1m30s500ms", Duration::from_millis(90_500)),
+        ] {
+            assert_eq!(
+                vars("every", value).cli_arg_duration("every").unwrap(),
+                expected,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_duration_args() {
+        for value in [
+            "",
+            "30",
+            "s",
+            "5 m",
+            "1.5h",
+            "10x",
+            "-5s",
+            "99999999999999999999d",
+        ] {
+            let err = vars("every", value).cli_arg_duration("every").unwrap_err();
+            assert!(
+                err.to_string()
+                    .starts_with("the argument every is not a valid duration"),
+                "{value}"
+            );
+        }
+        assert!(Vars::default().cli_arg_duration("every").is_err());
+    }
+
+    #[test]
+    fn can_parse_datetime_args() {
+        let since = vars("since", "2024-03-01T12:00:00+02:00")
+            .cli_arg_datetime("since")
+            .unwrap();
+        assert_eq!(since.to_rfc3339(), "2024-03-01T12:00:00+02:00");
+
+        let since = vars("since", "2024-03-01T12:00:00Z")
+            .cli_arg_datetime("since")
+            .unwrap();
+        assert_eq!(since.timestamp(), 1_709_294_400);
+    }
+
+    #[test]
+    fn rejects_malformed_datetime_args() {
+        for value in ["2024-03-01", "yesterday", "2024-13-01T00:00:00Z"] {
+            let err = vars("since", value).cli_arg_datetime("since").unwrap_err();
+            assert!(
+                err.to_string()
+                    .starts_with("the argument since is not a valid ISO 8601 date and time"),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_argv_rejects_empty_keys() {
+        assert!(Vars::parse_argv(&argv(&["--=value"])).is_err());
+        assert!(Vars::parse_argv(&argv(&["=value"])).is_err());
+    }
 }
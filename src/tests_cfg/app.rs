@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    app::AppContext,
+    cache,
+    controller::health::HealthRegistry,
+    environment::Environment,
+    storage::{self, Storage},
+    tests_cfg::config::test_config,
+};
+
+/// Creates an [`AppContext`] for unit tests, backed by an in-memory sqlite
+/// database, in-memory storage and a null cache.
+///
+/// # Panics
+/// When the in-memory database could not be created
+pub async fn get_app_context() -> AppContext<()> {
+    AppContext {
+        environment: Environment::Test,
+        #[cfg(feature = "with-db")]
+        db: sea_orm::Database::connect("sqlite::memory:").await.unwrap(),
+        queue: None,
+        config: test_config(),
+        mailer: None,
+        storage: Storage::single(storage::drivers::mem::new()).into(),
+        cache: cache::Cache::new(cache::drivers::null::new()).into(),
+        cancellation_token: CancellationToken::new(),
+        health: HealthRegistry::with_defaults(),
+        initializers: Arc::default(),
+        extra: None,
+    }
+}
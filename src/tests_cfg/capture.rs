@@ -0,0 +1,156 @@
+//! A `tracing` layer that captures events, for asserting log lines in tests.
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    subscriber::DefaultGuard,
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+/// A captured `tracing` event.
+#[derive(Debug, Clone)]
+pub struct CapturedEvent {
+    pub level: Level,
+    pub target: String,
+    pub fields: BTreeMap<String, String>,
+    /// The spans the event was emitted in, from the root
+    pub spans: Vec<CapturedSpan>,
+}
+
+/// A span an event was emitted in.
+#[derive(Debug, Clone)]
+pub struct CapturedSpan {
+    pub name: String,
+    pub target: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+impl CapturedEvent {
+    /// Get the event message.
+    #[must_use]
+    pub fn message(&self) -> Option<&str> {
+        self.field("message")
+    }
+
+    /// Get a recorded field value by name.
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+
+    /// Get a field value recorded on the closest enclosing span having it.
+    #[must_use]
+    pub fn span_field(&self, name: &str) -> Option<&str> {
+        self.spans
+            .iter()
+            .rev()
+            .find_map(|span| span.fields.get(name))
+            .map(String::as_str)
+    }
+}
+
+/// Collects all the events emitted while installed as the default subscriber.
+#[derive(Clone, Default)]
+pub struct Capture {
+    events: Arc<Mutex<Vec<CapturedEvent>>>,
+}
+
+impl Capture {
+    /// Install a subscriber with this capture layer as the thread default.
+    /// Events are captured as long as the returned guard is alive.
+    #[must_use]
+    pub fn set_default(&self) -> DefaultGuard {
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(self.clone()))
+    }
+
+    /// Get all the captured events.
+    ///
+    /// # Panics
+    /// When the events lock is poisoned
+    #[must_use]
+    pub fn events(&self) -> Vec<CapturedEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Get the captured events with the given message.
+    #[must_use]
+    pub fn events_with_message(&self, message: &str) -> Vec<CapturedEvent> {
+        self.events()
+            .into_iter()
+            .filter(|e| e.message() == Some(message))
+            .collect()
+    }
+}
+
+struct FieldsVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldsVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+/// The fields recorded on a span, kept in the span extensions.
+struct SpanFields(BTreeMap<String, String>);
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldsVisitor(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(SpanFields(fields)) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(&mut FieldsVisitor(fields));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldsVisitor(&mut fields));
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| CapturedSpan {
+                        name: span.name().to_string(),
+                        target: span.metadata().target().to_string(),
+                        fields: span
+                            .extensions()
+                            .get::<SpanFields>()
+                            .map(|SpanFields(fields)| fields.clone())
+                            .unwrap_or_default(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Ok(mut events) = self.events.lock() {
+            events.push(CapturedEvent {
+                level: *event.metadata().level(),
+                target: event.metadata().target().to_string(),
+                fields,
+                spans,
+            });
+        }
+    }
+}
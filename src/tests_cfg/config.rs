@@ -0,0 +1,53 @@
+use crate::{config, logger};
+
+/// Creates a configuration suitable for unit tests, with no external services
+/// configured.
+#[must_use]
+pub fn test_config() -> config::Config {
+    config::Config {
+        logger: config::Logger {
+            enable: false,
+            pretty_backtrace: false,
+            level: logger::LogLevel::Off,
+            format: logger::Format::Json,
+            log_panics: false,
+            override_filter: None,
+        },
+        server: config::Server {
+            binding: "localhost".to_string(),
+            port: 5150,
+            host: "localhost".to_string(),
+            ident: None,
+            shutdown_timeout: 30_000,
+            shutdown_pre_drain_delay: 0,
+            shutdown_grace_timeout: 10_000,
+            pagination: config::Pagination::default(),
+            health: config::Health::default(),
+            listener: config::Listener::default(),
+            connection: config::Connection::default(),
+            middlewares: config::Middlewares::default(),
+        },
+        #[cfg(feature = "with-db")]
+        database: config::Database {
+            uri: "sqlite::memory:".to_string(),
+            enable_logging: false,
+            min_connections: 1,
+            max_connections: 1,
+            connect_timeout: 500,
+            idle_timeout: 500,
+            acquire_timeout: None,
+            auto_migrate: false,
+            dangerously_truncate: false,
+            dangerously_recreate: false,
+        },
+        queue: None,
+        auth: None,
+        workers: config::Workers {
+            mode: config::WorkerMode::ForegroundBlocking,
+            queues: None,
+        },
+        mailer: None,
+        initializers: None,
+        settings: None,
+    }
+}
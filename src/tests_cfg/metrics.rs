@@ -0,0 +1,99 @@
+//! A `metrics` recorder that captures histogram values, for asserting
+//! recorded metrics in tests.
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use metrics::{
+    Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+
+/// A captured histogram value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedValue {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
+
+/// Collects the histogram values recorded while installed, counters and
+/// gauges are ignored.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let capture = MetricsCapture::default();
+/// metrics::with_local_recorder(&capture, || record_something());
+/// assert_eq!(capture.values("something"), vec![1.0]);
+/// ```
+#[derive(Clone, Default)]
+pub struct MetricsCapture {
+    values: Arc<Mutex<Vec<RecordedValue>>>,
+}
+
+impl MetricsCapture {
+    /// Get all the recorded histogram values.
+    ///
+    /// # Panics
+    /// When the values lock is poisoned
+    #[must_use]
+    pub fn recorded(&self) -> Vec<RecordedValue> {
+        self.values.lock().unwrap().clone()
+    }
+
+    /// Get the values recorded in the histogram with the given name.
+    #[must_use]
+    pub fn values(&self, name: &str) -> Vec<f64> {
+        self.recorded()
+            .into_iter()
+            .filter(|recorded| recorded.name == name)
+            .map(|recorded| recorded.value)
+            .collect()
+    }
+}
+
+struct CapturedHistogram {
+    name: String,
+    labels: BTreeMap<String, String>,
+    values: Arc<Mutex<Vec<RecordedValue>>>,
+}
+
+impl HistogramFn for CapturedHistogram {
+    fn record(&self, value: f64) {
+        if let Ok(mut values) = self.values.lock() {
+            values.push(RecordedValue {
+                name: self.name.clone(),
+                labels: self.labels.clone(),
+                value,
+            });
+        }
+    }
+}
+
+impl Recorder for MetricsCapture {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, _key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        Counter::noop()
+    }
+
+    fn register_gauge(&self, _key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(Arc::new(CapturedHistogram {
+            name: key.name().to_string(),
+            labels: key
+                .labels()
+                .map(|label| (label.key().to_string(), label.value().to_string()))
+                .collect(),
+            values: self.values.clone(),
+        }))
+    }
+}
@@ -1,2 +1,6 @@
+pub mod app;
+pub mod capture;
+pub mod config;
 #[cfg(feature = "with-db")]
 pub mod db;
+pub mod metrics;
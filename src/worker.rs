@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 pub use bb8::Pool;
+use sidekiq::{ChainIter, Job, RedisPool, ServerMiddleware, WorkerRef};
 pub use sidekiq::{Processor, RedisConnectionManager, Result, Worker};
+use tokio_util::task::TaskTracker;
 use tracing::error;
 
 use super::{app::AppContext, config::WorkerMode};
@@ -57,3 +61,20 @@ where
         Ok(())
     }
 }
+
+/// A processor middleware tracking the jobs in flight, so that the shutdown
+/// can wait for them.
+pub(crate) struct TrackJobs(pub TaskTracker);
+
+#[async_trait]
+impl ServerMiddleware for TrackJobs {
+    async fn call(
+        &self,
+        chain: ChainIter,
+        job: &Job,
+        worker: Arc<WorkerRef>,
+        redis: RedisPool,
+    ) -> Result<()> {
+        self.0.track_future(chain.next(job, worker, redis)).await
+    }
+}
@@ -0,0 +1,10 @@
+<html>
+
+<body>
+  Welcome {{ name }},
+  <a href="http://localhost/verify/{{ token }}">
+    Verify your account
+  </a>
+</body>
+
+</html>
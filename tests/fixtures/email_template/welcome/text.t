@@ -0,0 +1,3 @@
+Welcome {{ name }},
+
+  http://localhost/verify/{{ token }}